@@ -1,49 +1,139 @@
-use crossterm::event::{read, Event, KeyEvent, KeyEventKind};
+use crossterm::event::{poll, read, Event, KeyEvent, KeyEventKind};
 use std::{
+    cell::RefCell,
     env,
     io::Error,
+    mem,
     panic::{set_hook, take_hook},
+    path::Path,
+    rc::Rc,
+    thread,
+    time::Duration,
 };
+mod annotatedstring;
+mod annotation;
+mod bufferlist;
+mod clipboard;
 mod command;
 mod commandbar;
+mod diffview;
 mod documentstatus;
+mod encoding;
+mod filefinder;
+mod filetype;
+mod indentation;
+mod layout;
 mod line;
+mod lineending;
+mod linenumbers;
 mod messagebar;
 mod position;
+mod searchquery;
+mod settings;
 mod size;
 mod statusbar;
+mod tabbar;
+mod tabstops;
 mod terminal;
 mod uicomponent;
 mod view;
+mod window;
 
+use annotatedstring::AnnotatedString;
+use annotation::{Annotation, AnnotationType};
+use bufferlist::BufferList;
+use clipboard::Clipboard;
 use commandbar::CommandBar;
+use diffview::DiffView;
 use documentstatus::DocumentStatus;
+use encoding::Encoding;
+use filefinder::FileFinder;
+use filetype::FileType;
+use indentation::Indentation;
+use layout::{Layout, SplitDirection};
 use line::Line;
+use lineending::LineEnding;
+use linenumbers::LineNumbers;
 use messagebar::MessageBar;
 use position::Position;
+use searchquery::{SearchMode, SearchQuery};
+use settings::Settings;
 use size::Size;
 use statusbar::StatusBar;
+use tabbar::{Tab, TabBar};
+use tabstops::TabStops;
 use terminal::Terminal;
 use uicomponent::UIComponent;
-use view::View;
+use view::{Buffer, DiffKind, DiffLine, View};
+use window::Window;
 
 use self::command::{
-    Command::{self, Edit, Move, System},
-    Edit::InsertNewline,
-    System::{Dismiss, Quit, Resize, Save},
+    Command::{self, Edit, Move, Select, System},
+    Edit::{Insert, InsertNewline, InsertText},
+    Move::{Down, PageDown, PageUp, Up},
+    System::{
+        CloseWindow, ConvertLineEndings, Copy, Cut, CycleSearchMode, Dismiss, FindFile,
+        FocusGained, FocusNextWindow, NextBuffer, Paste, PickBuffer, PreviousBuffer, Quit,
+        ReopenWithEncoding, Replace, Resize, Save, Search, SelectTab, SplitHorizontally,
+        SplitVertically, ToggleSoftWrap,
+    },
 };
 pub const NAME: &str = env!("CARGO_PKG_NAME");
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 const QUIT_TIMES: u8 = 3;
+// How long to wait for input before checking whether the swap file needs to be written.
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+// The windows start below the tab bar.
+const LAYOUT_ORIGIN: Position = Position { row: 1, col: 0 };
+// How many buffers the buffer prompt lists at once.
+const MAX_LISTED_BUFFERS: usize = 5;
+
+#[derive(Eq, PartialEq, Default)]
+enum PromptType {
+    Search,
+    Save,
+    Encoding,
+    Replace,
+    ReplaceWith(String),
+    ReplaceConfirm,
+    Recover,
+    // The text can't be saved in the file's encoding. Holds the file name to save as, if any.
+    EncodingMismatch(Option<String>),
+    // The file changed on disk.
+    ExternalChange,
+    // The file changed on disk, and the changes are shown.
+    ExternalChangeDiff,
+    PickBuffer,
+    FindFile,
+    #[default]
+    None,
+}
+
+impl PromptType {
+    fn is_none(&self) -> bool {
+        *self == Self::None
+    }
+    // Whether something is shown instead of the windows during the prompt.
+    fn covers_windows(&self) -> bool {
+        matches!(self, Self::FindFile | Self::ExternalChangeDiff)
+    }
+}
 
 #[derive(Default)]
 pub struct Editor {
     should_quit: bool,
-    view: View,
-    status_bar: StatusBar,
+    buffers: BufferList,
+    tab_bar: TabBar,
+    windows: Layout,
+    file_finder: FileFinder,
+    diff_view: DiffView,
     message_bar: MessageBar,
-    command_bar: Option<CommandBar>,
+    command_bar: CommandBar,
+    prompt_type: PromptType,
+    search_mode: SearchMode,
+    clipboard: Clipboard,
+    settings: Settings,
     terminal_size: Size,
     title: String,
     quit_times: u8,
@@ -59,51 +149,102 @@ impl Editor {
         Terminal::initialize()?;
 
         let mut editor = Self::default();
-        let size = Terminal::size().unwrap_or_default();
-        editor.resize(size);
-        editor
-            .message_bar
-            .update_message("HELP: Ctrl-S = save | Ctrl-Q = quit");
+        editor.message_bar.update_message(
+            "HELP: Ctrl-S = save | Ctrl-Q = quit | Ctrl-F = find | Ctrl-P = open | Ctrl-B = buffers",
+        );
 
-        let args: Vec<String> = env::args().collect();
-        if let Some(file_name) = args.get(1) {
-            if editor.view.load(file_name).is_err() {
+        let mut file_names = Vec::new();
+        for arg in env::args().skip(1) {
+            if let Some(option) = arg.strip_prefix("--") {
+                if let Err(err) = editor.settings.apply(option) {
+                    editor.message_bar.update_message(&format!("ERR: {err}"));
+                }
+            } else {
+                file_names.push(arg);
+            }
+        }
+        for file_name in &file_names {
+            // The same file may be named twice, possibly spelled differently.
+            if editor.buffers.find_file(Path::new(file_name)).is_some() {
+                continue;
+            }
+            if let Ok(buffer) = Buffer::load(file_name) {
+                editor.warn_about_fallback_encoding(&buffer);
+                editor.buffers.push(buffer);
+            } else {
                 editor
                     .message_bar
                     .update_message(&format!("ERR: Could not open file: {file_name}"));
             }
         }
+        if editor.buffers.is_empty() {
+            editor.buffers.push(Buffer::default());
+        }
+        let mut view = editor.new_view();
+        if let Some(buffer) = editor.buffers.get(0) {
+            view.show_buffer(buffer);
+        }
+        editor.windows = Layout::new(view);
+        let size = Terminal::size().unwrap_or_default();
+        editor.resize(size);
+        editor.prompt_for_recovery();
 
         editor.refresh_status();
         Ok(editor)
     }
 
+    // Creates an empty view with the editor's settings applied.
+    fn new_view(&self) -> View {
+        let mut view = View::default();
+        view.set_line_numbers(self.settings.line_numbers);
+        view.set_soft_wrap(self.settings.soft_wrap);
+        view.set_tab_stops(self.settings.tab_stops);
+        view.set_indentation(self.settings.indentation);
+        view
+    }
+
     fn resize(&mut self, size: Size) {
         self.terminal_size = size;
-        self.view.resize(Size {
-            height: size.height.saturating_sub(2),
+        self.tab_bar.resize(Size {
+            height: 1,
             width: size.width,
         });
+        // The windows take up everything between the tab bar and the message bar,
+        // and the file finder covers them while it's open.
+        let layout_size = Size {
+            height: size.height.saturating_sub(2),
+            width: size.width,
+        };
+        self.windows.resize(layout_size);
+        self.file_finder.resize(layout_size);
+        self.diff_view.resize(layout_size);
         self.message_bar.resize(Size {
             height: 1,
             width: size.width,
         });
-        self.status_bar.resize(Size {
+        self.command_bar.resize(Size {
             height: 1,
             width: size.width,
         });
-        if let Some(command_bar) = &mut self.command_bar {
-            command_bar.resize(Size {
-                height: 1,
-                width: size.width,
-            });
-        }
     }
 
     fn refresh_status(&mut self) {
-        let status = self.view.get_status();
-        let title = format!("{} - {NAME}", status.file_name);
-        self.status_bar.update_status(status);
+        self.windows.refresh_status();
+        let current_buffer = self.windows.current().buffer();
+        let tabs = self
+            .buffers
+            .iter()
+            .map(|buffer| {
+                let buffer = buffer.borrow();
+                Tab {
+                    name: buffer.file_info.to_string(),
+                    is_modified: buffer.dirty,
+                }
+            })
+            .collect();
+        let current_index = self.buffers.index_of(&current_buffer).unwrap_or_default();
+        self.tab_bar.update_tabs(tabs, current_index);
+        let title = format!("{} - {NAME}", self.windows.current().get_status().file_name);
 
         if title != self.title && matches!(Terminal::set_title(&title), Ok(())) {
             self.title = title;
@@ -115,8 +256,10 @@ impl Editor {
             if self.should_quit {
                 break;
             }
-            match read() {
-                Ok(event) => self.evaluate_event(event),
+            // Only wait for input so long, so that unsaved changes reach the swap file while idle.
+            match poll(TICK_INTERVAL).and_then(|has_event| has_event.then(read).transpose()) {
+                Ok(Some(event)) => self.evaluate_event(event),
+                Ok(None) => {}
                 Err(err) => {
                     #[cfg(debug_assertions)]
                     {
@@ -124,15 +267,15 @@ impl Editor {
                     }
                 }
             }
-            let status = self.view.get_status();
-            self.status_bar.update_status(status);
+            self.buffers.update_swap_files();
+            self.refresh_status();
         }
     }
 
     fn evaluate_event(&mut self, event: Event) {
         let should_process = match &event {
             Event::Key(KeyEvent { kind, .. }) => kind == &KeyEventKind::Press,
-            Event::Resize(_, _) => true,
+            Event::Resize(_, _) | Event::Paste(_) | Event::FocusGained => true,
             _ => false,
         };
 
@@ -146,89 +289,670 @@ impl Editor {
     fn process_command(&mut self, command: Command) {
         match command {
             System(Quit) => {
-                if self.command_bar.is_none() {
+                if self.prompt_type.is_none() {
                     self.handle_quit();
                 }
             }
             System(Resize(size)) => self.resize(size),
-            _ => self.reset_quit_times(), // Reset quit times for all other commands
+            _ => {
+                self.reset_quit_times(); // Reset quit times for all other commands
+                match self.prompt_type {
+                    PromptType::Search => self.process_command_during_search(command),
+                    PromptType::Save => self.process_command_during_save(command),
+                    PromptType::Encoding => self.process_command_during_encoding(command),
+                    PromptType::Replace | PromptType::ReplaceWith(_) => {
+                        self.process_command_during_replace(command);
+                    }
+                    PromptType::ReplaceConfirm => {
+                        self.process_command_during_replace_confirm(&command);
+                    }
+                    PromptType::Recover => self.process_command_during_recover(&command),
+                    PromptType::EncodingMismatch(_) => {
+                        self.process_command_during_encoding_mismatch(&command);
+                    }
+                    PromptType::PickBuffer => self.process_command_during_pick_buffer(command),
+                    PromptType::FindFile => self.process_command_during_find_file(command),
+                    PromptType::ExternalChange | PromptType::ExternalChangeDiff => {
+                        self.process_command_during_external_change(&command);
+                    }
+                    PromptType::None => self.process_command_no_prompt(command),
+                }
+            }
         }
-
+    }
+    fn process_command_no_prompt(&mut self, command: Command) {
         match command {
-            System(Quit | Resize(_)) => {} // already handled above
-            System(Save) => {
-                if self.command_bar.is_none() {
-                    self.handle_save();
-                }
+            System(Quit | Resize(_) | Dismiss | CycleSearchMode) => {} // Quit and Resize are handled in process_command, there's nothing to dismiss or search
+            System(Search) => self.set_prompt(PromptType::Search),
+            System(Replace) => self.set_prompt(PromptType::Replace),
+            System(Save) => self.handle_save(),
+            System(ToggleSoftWrap) => {
+                let view = self.windows.current_mut();
+                view.set_soft_wrap(!view.soft_wrap());
             }
-            System(Dismiss) => {
-                if self.command_bar.is_some() {
-                    self.dismiss_prompt();
-                    self.message_bar.update_message("Save aborted.");
-                }
+            System(ConvertLineEndings) => {
+                let line_ending = self.windows.current_mut().convert_line_endings();
+                self.message_bar
+                    .update_message(&format!("Line endings converted to {line_ending}."));
             }
-            Edit(edit_command) => {
-                if let Some(command_bar) = &mut self.command_bar {
-                    if matches!(edit_command, InsertNewline) {
-                        let file_name = command_bar.value();
-                        self.dismiss_prompt();
-                        self.save(Some(&file_name));
-                    } else {
-                        command_bar.handle_edit_command(edit_command);
-                    }
+            System(ReopenWithEncoding) => self.handle_reopen_with_encoding(),
+            System(FocusGained) => self.check_external_change(),
+            System(NextBuffer) => {
+                let next = self.buffers.next_after(&self.windows.current().buffer());
+                self.show_buffer(next);
+            }
+            System(PreviousBuffer) => {
+                let previous = self
+                    .buffers
+                    .previous_before(&self.windows.current().buffer());
+                self.show_buffer(previous);
+            }
+            System(PickBuffer) => self.set_prompt(PromptType::PickBuffer),
+            System(FindFile) => self.handle_find_file(),
+            System(SelectTab(index)) => self.show_buffer(self.buffers.get(index)),
+            System(SplitHorizontally) => self.windows.split(SplitDirection::Horizontal),
+            System(SplitVertically) => self.windows.split(SplitDirection::Vertical),
+            System(CloseWindow) => {
+                if self.windows.close_current() {
+                    self.check_external_change();
                 } else {
-                    self.view.handle_edit_command(edit_command);
+                    self.message_bar
+                        .update_message("The last window can't be closed.");
                 }
             }
-            Move(move_command) => {
-                if self.command_bar.is_none() {
-                    self.view.handle_move_command(move_command);
+            System(FocusNextWindow) => {
+                self.windows.focus_next();
+                self.check_external_change();
+            }
+            System(Copy) => {
+                if let Some(text) = self.windows.current_mut().copy_selection() {
+                    self.copy_to_clipboard(text);
                 }
             }
+            System(Cut) => {
+                if let Some(text) = self.windows.current_mut().cut_selection() {
+                    self.copy_to_clipboard(text);
+                }
+            }
+            System(Paste) => self
+                .windows
+                .current_mut()
+                .handle_edit_command(InsertText(self.clipboard.text().to_string())),
+            Edit(edit_command) => self.windows.current_mut().handle_edit_command(edit_command),
+            Move(move_command) => self.windows.current_mut().handle_move_command(move_command),
+            Select(move_command) => self
+                .windows
+                .current_mut()
+                .handle_select_command(move_command),
         }
     }
-    fn dismiss_prompt(&mut self) {
-        self.command_bar = None;
-        self.message_bar.set_needs_redraw(true);
+
+    fn copy_to_clipboard(&mut self, text: String) {
+        if self.settings.osc52 && Terminal::copy_to_clipboard(&text).is_err() {
+            self.message_bar
+                .update_message("Could not copy to the system clipboard.");
+        }
+        self.clipboard.set(text);
     }
-    fn show_prompt(&mut self) {
-        let mut command_bar = CommandBar::default();
-        command_bar.set_prompt("Save as: ");
-        command_bar.resize(Size {
-            height: 1,
-            width: self.terminal_size.width,
-        });
-        command_bar.set_needs_redraw(true);
-        self.command_bar = Some(command_bar);
+
+    // region: prompts
+
+    fn set_prompt(&mut self, prompt_type: PromptType) {
+        match prompt_type {
+            PromptType::None => self.message_bar.set_needs_redraw(true),
+            PromptType::Search => self.windows.current_mut().enter_search(),
+            PromptType::FindFile => self.file_finder.set_needs_redraw(true),
+            _ => {}
+        }
+        if self.prompt_type.covers_windows() {
+            self.windows.set_needs_redraw(true);
+        }
+        self.prompt_type = prompt_type;
+        self.command_bar.clear_value();
+        self.command_bar.set_prompt(&self.prompt_text());
+    }
+    fn prompt_text(&self) -> String {
+        let mode = self.search_mode.label();
+        match &self.prompt_type {
+            PromptType::None => String::new(),
+            PromptType::Save => String::from("Save as: "),
+            PromptType::Encoding => format!("Reopen with encoding ({}): ", Encoding::NAMES),
+            PromptType::Search => {
+                format!("Search [{mode}] (Esc cancel, Up/Down navigate, Ctrl-T mode): ")
+            }
+            PromptType::Replace => format!("Replace [{mode}] (Ctrl-T mode): "),
+            PromptType::ReplaceWith(query) => format!("Replace [{mode}] {query} with: "),
+            PromptType::ReplaceConfirm => {
+                String::from("Replace this occurrence? (y)es, (n)o, (a)ll, (q)uit")
+            }
+            PromptType::ExternalChange => {
+                String::from("File changed on disk: (r)eload, (o)verwrite, (d)iff, (c)ancel")
+            }
+            PromptType::ExternalChangeDiff => {
+                String::from("(r)eload, (o)verwrite, (d)iff off, (c)ancel, Up/Down scroll")
+            }
+            PromptType::PickBuffer => {
+                format!(
+                    "Switch to buffer ({}): ",
+                    self.buffer_list(&self.command_bar.value())
+                )
+            }
+            PromptType::FindFile => format!(
+                "Open file ({}/{}): ",
+                self.file_finder.match_count(),
+                self.file_finder.file_count()
+            ),
+            PromptType::EncodingMismatch(_) => format!(
+                "The text doesn't fit {}. Save as UTF-8 instead? (y/n)",
+                self.windows.current().get_status().encoding
+            ),
+            PromptType::Recover => format!(
+                "(r)ecover or (d)iscard unsaved changes from an earlier session? {}",
+                self.windows.current().get_status().file_name
+            ),
+        }
+    }
+    fn cycle_search_mode(&mut self) {
+        self.search_mode = self.search_mode.next();
+        self.command_bar.set_prompt(&self.prompt_text());
+    }
+
+    // endregion
+
+    // region: save
+
+    fn process_command_during_save(&mut self, command: Command) {
+        match command {
+            System(
+                Quit | Resize(_) | Search | Replace | Save | CycleSearchMode | ToggleSoftWrap
+                | ConvertLineEndings | ReopenWithEncoding | FocusGained | NextBuffer
+                | PreviousBuffer | PickBuffer | FindFile | SelectTab(_) | SplitHorizontally
+                | SplitVertically | CloseWindow | FocusNextWindow | Cut | Copy | Paste,
+            )
+            | Move(_)
+            | Select(_) => {} // Not applicable during save, Resize and Quit are handled in process_command
+            System(Dismiss) => {
+                self.set_prompt(PromptType::None);
+                self.message_bar.update_message("Save aborted.");
+            }
+            Edit(InsertNewline) => {
+                let file_name = self.command_bar.value();
+                self.save(Some(&file_name));
+                self.set_prompt(PromptType::None);
+            }
+            Edit(edit_command) => self.command_bar.handle_edit_command(edit_command),
+        }
     }
     fn handle_save(&mut self) {
-        if self.view.is_file_loaded() {
+        if self.windows.current().is_changed_on_disk() {
+            self.set_prompt(PromptType::ExternalChange);
+        } else if self.windows.current().is_file_loaded() {
             self.save(None);
         } else {
-            self.show_prompt();
+            self.set_prompt(PromptType::Save);
         }
     }
 
     fn save(&mut self, file_name: Option<&str>) {
+        if !self.windows.current().fits_encoding() {
+            self.set_prompt(PromptType::EncodingMismatch(file_name.map(String::from)));
+            return;
+        }
         let result = if let Some(name) = file_name {
-            self.view.save_as(name)
+            self.windows.current_mut().save_as(name)
+        } else {
+            self.windows.current_mut().save()
+        };
+        match result {
+            Ok(()) => self.message_bar.update_message("File saved successfully."),
+            Err(err) => self
+                .message_bar
+                .update_message(&format!("ERR: Could not save file: {err}")),
+        }
+    }
+
+    fn process_command_during_encoding_mismatch(&mut self, command: &Command) {
+        match command {
+            Edit(Insert('y' | 'Y')) => {
+                let PromptType::EncodingMismatch(file_name) = mem::take(&mut self.prompt_type)
+                else {
+                    return;
+                };
+                self.set_prompt(PromptType::None);
+                self.windows.current_mut().set_encoding(Encoding::Utf8);
+                self.save(file_name.as_deref());
+            }
+            Edit(Insert('n' | 'N')) | System(Dismiss) => {
+                self.set_prompt(PromptType::None);
+                self.message_bar.update_message("Save aborted.");
+            }
+            _ => {} // Ignore everything else and keep asking
+        }
+    }
+
+    // endregion
+
+    // region: encoding
+
+    fn process_command_during_encoding(&mut self, command: Command) {
+        match command {
+            System(
+                Quit | Resize(_) | Search | Replace | Save | CycleSearchMode | ToggleSoftWrap
+                | ConvertLineEndings | ReopenWithEncoding | FocusGained | NextBuffer
+                | PreviousBuffer | PickBuffer | FindFile | SelectTab(_) | SplitHorizontally
+                | SplitVertically | CloseWindow | FocusNextWindow | Cut | Copy | Paste,
+            )
+            | Move(_)
+            | Select(_) => {} // Not applicable while choosing an encoding, Resize and Quit are handled in process_command
+            System(Dismiss) => {
+                self.set_prompt(PromptType::None);
+                self.message_bar.update_message("Reopen aborted.");
+            }
+            Edit(InsertNewline) => {
+                let name = self.command_bar.value();
+                self.set_prompt(PromptType::None);
+                self.reopen_with_encoding(&name);
+            }
+            Edit(edit_command) => self.command_bar.handle_edit_command(edit_command),
+        }
+    }
+    fn handle_reopen_with_encoding(&mut self) {
+        if !self.windows.current().is_file_loaded() {
+            self.message_bar
+                .update_message("There is no file to reopen.");
+        } else if self.windows.current().get_status().is_modified {
+            self.message_bar
+                .update_message("File has unsaved changes. Save them before reopening.");
+        } else {
+            self.set_prompt(PromptType::Encoding);
+        }
+    }
+    fn reopen_with_encoding(&mut self, name: &str) {
+        let Some(encoding) = Encoding::from_name(name) else {
+            self.message_bar
+                .update_message(&format!("Unknown encoding: {name}"));
+            return;
+        };
+        match self.windows.current_mut().reload_as(encoding) {
+            Ok(()) => self
+                .message_bar
+                .update_message(&format!("Reopened as {encoding}.")),
+            Err(err) => self
+                .message_bar
+                .update_message(&format!("ERR: Could not reopen as {encoding}: {err}")),
+        }
+    }
+
+    // endregion
+
+    // region: buffers
+
+    fn process_command_during_pick_buffer(&mut self, command: Command) {
+        match command {
+            System(Dismiss) => self.set_prompt(PromptType::None),
+            Edit(InsertNewline) => {
+                let query = self.command_bar.value();
+                self.set_prompt(PromptType::None);
+                if let Some(index) = self.buffers.find(&query) {
+                    self.show_buffer(self.buffers.get(index));
+                } else {
+                    self.message_bar
+                        .update_message(&format!("No buffer matches {query}."));
+                }
+            }
+            Edit(edit_command) => {
+                self.command_bar.handle_edit_command(edit_command);
+                // The list narrows down to the buffers matching what has been typed so far.
+                self.command_bar.set_prompt(&self.prompt_text());
+            }
+            System(_) | Move(_) | Select(_) => {} // Not applicable while picking a buffer, Resize and Quit are handled in process_command
+        }
+    }
+    // Shows the given buffer in the current window.
+    fn show_buffer(&mut self, buffer: Option<Rc<RefCell<Buffer>>>) {
+        if let Some(buffer) = buffer {
+            self.windows.current_mut().show_buffer(buffer);
+            self.check_external_change();
+        }
+    }
+    // Lists the buffers matching `query` by number, marking the current one and those with
+    // unsaved changes. At most `MAX_LISTED_BUFFERS` are listed, starting near the current one,
+    // and the number of those left out on either side is shown as "+N more".
+    fn buffer_list(&self, query: &str) -> String {
+        let current_buffer = self.windows.current().buffer();
+        let query = query.trim();
+        let indices: Vec<usize> = if query.parse::<usize>().is_ok() {
+            (0..self.buffers.len()).collect()
+        } else {
+            self.buffers.matching(query).collect()
+        };
+        let current_position = self
+            .buffers
+            .index_of(&current_buffer)
+            .and_then(|current| indices.iter().position(|&index| index == current))
+            .unwrap_or(0);
+        let start = current_position
+            .saturating_sub(MAX_LISTED_BUFFERS.saturating_div(2))
+            .min(indices.len().saturating_sub(MAX_LISTED_BUFFERS));
+        let end = start.saturating_add(MAX_LISTED_BUFFERS).min(indices.len());
+        let mut entries = Vec::new();
+        if start > 0 {
+            entries.push(format!("+{start} more"));
+        }
+        for &index in indices.get(start..end).unwrap_or_default() {
+            let Some(buffer) = self.buffers.get(index) else {
+                continue;
+            };
+            let current = if Rc::ptr_eq(&buffer, &current_buffer) {
+                ">"
+            } else {
+                ""
+            };
+            let buffer = buffer.borrow();
+            let modified = if buffer.dirty { "*" } else { "" };
+            entries.push(format!(
+                "{current}{} {}{modified}",
+                index.saturating_add(1),
+                buffer.file_info
+            ));
+        }
+        if end < indices.len() {
+            entries.push(format!("+{} more", indices.len().saturating_sub(end)));
+        }
+        entries.join(", ")
+    }
+
+    // endregion
+
+    // region: find file
+
+    fn handle_find_file(&mut self) {
+        match self.file_finder.open(Path::new(".")) {
+            Ok(()) => self.set_prompt(PromptType::FindFile),
+            Err(err) => self
+                .message_bar
+                .update_message(&format!("ERR: Could not list files: {err}")),
+        }
+    }
+    fn process_command_during_find_file(&mut self, command: Command) {
+        match command {
+            System(Dismiss) => self.set_prompt(PromptType::None),
+            Edit(InsertNewline) => {
+                let path = self.file_finder.selected_path();
+                self.set_prompt(PromptType::None);
+                if let Some(path) = path {
+                    self.open_file(&path);
+                }
+            }
+            Move(Up) => self.file_finder.select_previous(1),
+            Move(Down) => self.file_finder.select_next(1),
+            Move(PageUp) => self
+                .file_finder
+                .select_previous(self.file_finder.page_size()),
+            Move(PageDown) => self.file_finder.select_next(self.file_finder.page_size()),
+            Edit(edit_command) => {
+                self.command_bar.handle_edit_command(edit_command);
+                self.file_finder.update_query(&self.command_bar.value());
+                self.command_bar.set_prompt(&self.prompt_text());
+            }
+            System(_) | Move(_) | Select(_) => {} // Not applicable while finding a file, Resize and Quit are handled in process_command
+        }
+    }
+    // Shows the file in the current window, loading it into a new buffer unless it's open already.
+    fn open_file(&mut self, path: &Path) {
+        if let Some(buffer) = self.buffers.find_file(path) {
+            self.show_buffer(Some(buffer));
+            return;
+        }
+        let file_name = path.to_string_lossy();
+        match Buffer::load(&file_name) {
+            Ok(buffer) => {
+                self.warn_about_fallback_encoding(&buffer);
+                let buffer = self.buffers.push(buffer);
+                self.windows.current_mut().show_buffer(buffer);
+                self.prompt_for_recovery();
+            }
+            Err(err) => self
+                .message_bar
+                .update_message(&format!("ERR: Could not open file: {file_name}: {err}")),
+        }
+    }
+    // Latin-1 is only ever picked as a fallback when loading, as any file decodes as Latin-1.
+    fn warn_about_fallback_encoding(&mut self, buffer: &Buffer) {
+        if buffer.encoding == Encoding::Latin1 {
+            self.message_bar.update_message(&format!(
+                "{} is not valid UTF-8 or UTF-16, opened as Latin-1. Alt-E to reopen.",
+                buffer.file_info
+            ));
+        }
+    }
+
+    // endregion
+
+    // region: recover
+
+    // Asks about the first buffer that has unsaved changes left over from an earlier session.
+    fn prompt_for_recovery(&mut self) {
+        let recoverable = self
+            .buffers
+            .iter()
+            .find(|buffer| buffer.borrow().has_recoverable_swap_file())
+            .map(Rc::clone);
+        if let Some(buffer) = recoverable {
+            self.windows.current_mut().show_buffer(buffer);
+            self.set_prompt(PromptType::Recover);
+        }
+    }
+    fn process_command_during_recover(&mut self, command: &Command) {
+        match command {
+            Edit(Insert('r' | 'R')) => {
+                self.set_prompt(PromptType::None);
+                match self.windows.current_mut().recover_swap_file() {
+                    Ok(()) => self
+                        .message_bar
+                        .update_message("Recovered unsaved changes. Save to keep them."),
+                    Err(err) => self
+                        .message_bar
+                        .update_message(&format!("ERR: Could not recover changes: {err}")),
+                }
+                self.prompt_for_recovery();
+            }
+            Edit(Insert('d' | 'D')) => {
+                self.set_prompt(PromptType::None);
+                self.windows.current_mut().discard_swap_file();
+                self.message_bar
+                    .update_message("Discarded unsaved changes from the earlier session.");
+                self.prompt_for_recovery();
+            }
+            _ => {} // Ignore everything else and keep asking
+        }
+    }
+
+    // endregion
+
+    // region: external changes
+
+    fn check_external_change(&mut self) {
+        if !self.windows.current().is_changed_on_disk() {
+            return;
+        }
+        if self.settings.auto_reload && !self.windows.current().get_status().is_modified {
+            self.reload();
         } else {
-            self.view.save()
+            self.set_prompt(PromptType::ExternalChange);
+        }
+    }
+    fn process_command_during_external_change(&mut self, command: &Command) {
+        match command {
+            Edit(Insert('r' | 'R')) => {
+                self.set_prompt(PromptType::None);
+                self.reload();
+            }
+            Edit(Insert('o' | 'O')) => {
+                self.set_prompt(PromptType::None);
+                self.save(None);
+            }
+            Edit(Insert('d' | 'D')) if self.prompt_type == PromptType::ExternalChangeDiff => {
+                self.set_prompt(PromptType::ExternalChange);
+            }
+            Edit(Insert('d' | 'D')) => match self.windows.current().diff_with_disk() {
+                Ok(diff) if diff.is_empty() => self
+                    .message_bar
+                    .update_message("The text is the same as on disk."),
+                Ok(diff) => {
+                    self.diff_view.show(diff);
+                    self.set_prompt(PromptType::ExternalChangeDiff);
+                }
+                Err(err) => self
+                    .message_bar
+                    .update_message(&format!("ERR: Could not compare with the file: {err}")),
+            },
+            Move(Up) => self.diff_view.scroll_up(1),
+            Move(Down) => self.diff_view.scroll_down(1),
+            Move(PageUp) => self.diff_view.scroll_up(self.diff_view.page_size()),
+            Move(PageDown) => self.diff_view.scroll_down(self.diff_view.page_size()),
+            Edit(Insert('c' | 'C')) | System(Dismiss) => {
+                self.set_prompt(PromptType::None);
+                self.message_bar
+                    .update_message("Left the file on disk as it is.");
+            }
+            _ => {} // Ignore everything else and keep asking
+        }
+    }
+    fn reload(&mut self) {
+        match self.windows.current_mut().reload() {
+            Ok(()) => self
+                .message_bar
+                .update_message("Reloaded the file after it changed on disk."),
+            Err(err) => self
+                .message_bar
+                .update_message(&format!("ERR: Could not reload file: {err}")),
+        }
+    }
+
+    // endregion
+
+    // region: search
+
+    fn process_command_during_search(&mut self, command: Command) {
+        match command {
+            System(Dismiss) => {
+                self.set_prompt(PromptType::None);
+                self.windows.current_mut().dismiss_search();
+            }
+            Edit(InsertNewline) => {
+                self.set_prompt(PromptType::None);
+                self.windows.current_mut().exit_search();
+            }
+            Edit(edit_command) => {
+                self.command_bar.handle_edit_command(edit_command);
+                self.update_search();
+            }
+            System(CycleSearchMode) => {
+                self.cycle_search_mode();
+                self.update_search();
+            }
+            Move(Down) => self.windows.current_mut().search_next(),
+            Move(Up) => self.windows.current_mut().search_prev(),
+            System(
+                Quit | Resize(_) | Search | Replace | Save | ToggleSoftWrap | ConvertLineEndings
+                | ReopenWithEncoding | FocusGained | NextBuffer | PreviousBuffer | PickBuffer
+                | FindFile | SelectTab(_) | SplitHorizontally | SplitVertically | CloseWindow
+                | FocusNextWindow | Cut | Copy | Paste,
+            )
+            | Move(_)
+            | Select(_) => {} // Not applicable during search, Resize and Quit are handled in process_command
+        }
+    }
+    fn update_search(&mut self) {
+        // Incomplete regular expressions are ignored until they become valid.
+        if let Ok(query) = SearchQuery::new(&self.command_bar.value(), self.search_mode) {
+            self.windows.current_mut().search(query);
+        }
+    }
+
+    // endregion
+
+    // region: replace
+
+    fn process_command_during_replace(&mut self, command: Command) {
+        match command {
+            System(Dismiss) => {
+                self.set_prompt(PromptType::None);
+                self.message_bar.update_message("Replace aborted.");
+            }
+            Edit(InsertNewline) => {
+                let value = self.command_bar.value();
+                match mem::take(&mut self.prompt_type) {
+                    PromptType::ReplaceWith(query) => self.start_replace(&query, &value),
+                    _ => self.set_prompt(PromptType::ReplaceWith(value)),
+                }
+            }
+            Edit(edit_command) => self.command_bar.handle_edit_command(edit_command),
+            System(CycleSearchMode) => self.cycle_search_mode(),
+            System(
+                Quit | Resize(_) | Search | Replace | Save | ToggleSoftWrap | ConvertLineEndings
+                | ReopenWithEncoding | FocusGained | NextBuffer | PreviousBuffer | PickBuffer
+                | FindFile | SelectTab(_) | SplitHorizontally | SplitVertically | CloseWindow
+                | FocusNextWindow | Cut | Copy | Paste,
+            )
+            | Move(_)
+            | Select(_) => {} // Not applicable during replace, Resize and Quit are handled in process_command
+        }
+    }
+    fn process_command_during_replace_confirm(&mut self, command: &Command) {
+        let has_more_matches = match command {
+            Edit(Insert('y' | 'Y')) => self.windows.current_mut().replace_current(),
+            Edit(Insert('n' | 'N')) => self.windows.current_mut().skip_current(),
+            Edit(Insert('a' | 'A')) => {
+                self.windows.current_mut().replace_all();
+                false
+            }
+            Edit(Insert('q' | 'Q')) | System(Dismiss) => false,
+            _ => true, // Ignore everything else and keep asking
+        };
+        if !has_more_matches {
+            let replaced = self.windows.current_mut().exit_replace();
+            self.set_prompt(PromptType::None);
+            self.message_bar
+                .update_message(&format!("Replaced {replaced} occurrence(s)."));
+        }
+    }
+    fn start_replace(&mut self, query: &str, replacement: &str) {
+        let Ok(search_query) = SearchQuery::new(query, self.search_mode) else {
+            self.set_prompt(PromptType::None);
+            self.message_bar
+                .update_message(&format!("Invalid regular expression: {query}"));
+            return;
         };
-        if result.is_ok() {
-            self.message_bar.update_message("File saved successfully.");
+        if self
+            .windows
+            .current_mut()
+            .enter_replace(search_query, replacement)
+        {
+            self.set_prompt(PromptType::ReplaceConfirm);
         } else {
-            self.message_bar.update_message("Error writing file!");
+            self.windows.current_mut().exit_replace();
+            self.set_prompt(PromptType::None);
+            self.message_bar
+                .update_message(&format!("No matches found for {query}."));
         }
     }
+
+    // endregion
+
     // clippy::arithmetic_side_effects: quit_times is guaranteed to be between 0 and QUIT_TIMES
     #[allow(clippy::arithmetic_side_effects)]
     fn handle_quit(&mut self) {
-        if !self.view.get_status().is_modified || self.quit_times + 1 == QUIT_TIMES {
+        let modified_file_names = self.buffers.modified_file_names();
+        if modified_file_names.is_empty() || self.quit_times + 1 == QUIT_TIMES {
             self.should_quit = true;
-        } else if self.view.get_status().is_modified {
+            // Quitting deliberately, so there is nothing left to recover.
+            self.buffers.remove_swap_files();
+        } else {
             self.message_bar.update_message(&format!(
-                "WARNING! File has unsaved changes. Press Ctrl-Q {} more times to quit.",
+                "WARNING! Unsaved changes in {}. Press Ctrl-Q {} more times to quit.",
+                modified_file_names.join(", "),
                 QUIT_TIMES - self.quit_times - 1
             ));
 
@@ -247,25 +971,34 @@ impl Editor {
         }
         let bottom_bar_row = self.terminal_size.height.saturating_sub(1);
         let _ = Terminal::hide_caret();
-        if let Some(command_bar) = &mut self.command_bar {
-            command_bar.render(bottom_bar_row);
+        if self.prompt_type.is_none() {
+            self.message_bar.render(Position {
+                row: bottom_bar_row,
+                col: 0,
+            });
         } else {
-            self.message_bar.render(bottom_bar_row);
+            self.command_bar.render(Position {
+                row: bottom_bar_row,
+                col: 0,
+            });
         }
         if self.terminal_size.height > 1 {
-            self.status_bar
-                .render(self.terminal_size.height.saturating_sub(2));
+            self.tab_bar.render(Position::default());
         }
         if self.terminal_size.height > 2 {
-            self.view.render(0);
+            match self.prompt_type {
+                PromptType::FindFile => self.file_finder.render(LAYOUT_ORIGIN),
+                PromptType::ExternalChangeDiff => self.diff_view.render(LAYOUT_ORIGIN),
+                _ => self.windows.render(LAYOUT_ORIGIN),
+            }
         }
-        let new_caret_pos = if let Some(command_bar) = &self.command_bar {
+        let new_caret_pos = if self.prompt_type.is_none() {
+            self.windows.caret_position(LAYOUT_ORIGIN)
+        } else {
             Position {
                 row: bottom_bar_row,
-                col: command_bar.caret_position_col(),
+                col: self.command_bar.caret_position_col(),
             }
-        } else {
-            self.view.caret_position()
         };
 
         let _ = Terminal::move_caret_to(new_caret_pos);
@@ -276,6 +1009,10 @@ impl Editor {
 
 impl Drop for Editor {
     fn drop(&mut self) {
+        // Don't wait for the next tick if we're going down, so no unsaved change gets lost.
+        if thread::panicking() {
+            self.buffers.write_swap_files();
+        }
         let _ = Terminal::terminate();
         if self.should_quit {
             let _ = Terminal::print("Goodbye.\r\n");
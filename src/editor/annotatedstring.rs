@@ -0,0 +1,45 @@
+use std::fmt::{self, Display};
+
+use super::AnnotationType;
+
+pub struct AnnotatedStringPart {
+    pub string: String,
+    pub annotation_type: Option<AnnotationType>,
+}
+
+/// A string made up of consecutive parts, each of which may carry an annotation.
+#[derive(Default)]
+pub struct AnnotatedString {
+    parts: Vec<AnnotatedStringPart>,
+}
+
+impl AnnotatedString {
+    /// Appends `string`, merging it into the last part if that has the same annotation.
+    pub fn push(&mut self, string: &str, annotation_type: Option<AnnotationType>) {
+        match self.parts.last_mut() {
+            Some(last) if last.annotation_type == annotation_type => last.string.push_str(string),
+            _ => self.parts.push(AnnotatedStringPart {
+                string: string.to_string(),
+                annotation_type,
+            }),
+        }
+    }
+    /// Appends all parts of `other`.
+    pub fn append(&mut self, other: &Self) {
+        for part in other.parts() {
+            self.push(&part.string, part.annotation_type);
+        }
+    }
+    pub fn parts(&self) -> impl Iterator<Item = &AnnotatedStringPart> {
+        self.parts.iter()
+    }
+}
+
+impl Display for AnnotatedString {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for part in &self.parts {
+            write!(formatter, "{}", part.string)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,24 @@
+use std::ops::Range;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum AnnotationType {
+    Selection,
+    Keyword,
+    Type,
+    String,
+    Number,
+    Comment,
+    LineNumber,
+    CurrentLineNumber,
+    ActiveTab,
+    Match,
+    Added,
+    Removed,
+}
+
+/// Marks a range of graphemes within a line for special rendering.
+#[derive(Clone, Debug)]
+pub struct Annotation {
+    pub annotation_type: AnnotationType,
+    pub range: Range<usize>,
+}
@@ -0,0 +1,110 @@
+use std::{cell::RefCell, fs, path::Path, rc::Rc};
+
+use super::Buffer;
+
+/// The open buffers in the order they were opened. Windows show them through their views.
+#[derive(Default)]
+pub struct BufferList {
+    buffers: Vec<Rc<RefCell<Buffer>>>,
+}
+
+impl BufferList {
+    pub fn push(&mut self, buffer: Buffer) -> Rc<RefCell<Buffer>> {
+        let buffer = Rc::new(RefCell::new(buffer));
+        self.buffers.push(Rc::clone(&buffer));
+        buffer
+    }
+    pub fn len(&self) -> usize {
+        self.buffers.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.buffers.is_empty()
+    }
+    pub fn get(&self, index: usize) -> Option<Rc<RefCell<Buffer>>> {
+        self.buffers.get(index).map(Rc::clone)
+    }
+    pub fn index_of(&self, buffer: &Rc<RefCell<Buffer>>) -> Option<usize> {
+        self.buffers
+            .iter()
+            .position(|other| Rc::ptr_eq(other, buffer))
+    }
+    /// Returns the buffer after `buffer`, wrapping around at the end.
+    pub fn next_after(&self, buffer: &Rc<RefCell<Buffer>>) -> Option<Rc<RefCell<Buffer>>> {
+        let next = self
+            .index_of(buffer)
+            .map_or(0, |index| index.saturating_add(1));
+        self.get(next).or_else(|| self.get(0))
+    }
+    /// Returns the buffer before `buffer`, wrapping around at the start.
+    pub fn previous_before(&self, buffer: &Rc<RefCell<Buffer>>) -> Option<Rc<RefCell<Buffer>>> {
+        self.index_of(buffer)
+            .and_then(|index| index.checked_sub(1))
+            .map_or_else(
+                || self.get(self.buffers.len().saturating_sub(1)),
+                |previous| self.get(previous),
+            )
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &Rc<RefCell<Buffer>>> {
+        self.buffers.iter()
+    }
+
+    /// Finds a buffer by its 1-based number or, failing that, by part of its file name.
+    pub fn find(&self, query: &str) -> Option<usize> {
+        let query = query.trim();
+        if let Ok(number) = query.parse::<usize>() {
+            return number
+                .checked_sub(1)
+                .filter(|&index| index < self.buffers.len());
+        }
+        self.matching(query).next()
+    }
+    /// Returns the indices of the buffers whose file name contains `query`.
+    pub fn matching<'a>(&'a self, query: &'a str) -> impl Iterator<Item = usize> + 'a {
+        self.buffers
+            .iter()
+            .enumerate()
+            .filter(move |(_, buffer)| buffer.borrow().file_info.to_string().contains(query))
+            .map(|(index, _)| index)
+    }
+    /// Finds the buffer of the file at `path`, however the path is spelled.
+    pub fn find_file(&self, path: &Path) -> Option<Rc<RefCell<Buffer>>> {
+        let path = fs::canonicalize(path).ok()?;
+        self.buffers
+            .iter()
+            .find(|buffer| {
+                buffer
+                    .borrow()
+                    .file_info
+                    .get_path()
+                    .and_then(|other| fs::canonicalize(other).ok())
+                    .is_some_and(|other| other == path)
+            })
+            .map(Rc::clone)
+    }
+    /// Returns the file names of all buffers with unsaved changes.
+    pub fn modified_file_names(&self) -> Vec<String> {
+        self.buffers
+            .iter()
+            .map(|buffer| buffer.borrow())
+            .filter(|buffer| buffer.dirty)
+            .map(|buffer| buffer.file_info.to_string())
+            .collect()
+    }
+
+    // The swap files are a safety net, so failing to write them must not get in the way of editing.
+    pub fn update_swap_files(&self) {
+        for buffer in &self.buffers {
+            let _ = buffer.borrow_mut().update_swap_file();
+        }
+    }
+    pub fn write_swap_files(&self) {
+        for buffer in &self.buffers {
+            let _ = buffer.borrow_mut().write_swap_file();
+        }
+    }
+    pub fn remove_swap_files(&self) {
+        for buffer in &self.buffers {
+            buffer.borrow_mut().remove_swap_file();
+        }
+    }
+}
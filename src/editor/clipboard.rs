@@ -0,0 +1,14 @@
+/// The editor-wide clipboard shared by cut, copy and paste.
+#[derive(Default)]
+pub struct Clipboard {
+    text: String,
+}
+
+impl Clipboard {
+    pub fn set(&mut self, text: String) {
+        self.text = text;
+    }
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
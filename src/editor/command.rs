@@ -1,8 +1,8 @@
 use crossterm::event::{
     Event,
     KeyCode::{
-        self, Backspace, Char, Delete, Down, End, Enter, Home, Left, PageDown, PageUp, Right, Tab,
-        Up,
+        self, BackTab, Backspace, Char, Delete, Down, End, Enter, Home, Left, PageDown, PageUp,
+        Right, Tab, Up,
     },
     KeyEvent, KeyModifiers,
 };
@@ -47,12 +47,29 @@ impl TryFrom<KeyEvent> for Move {
         }
     }
 }
-#[derive(Clone, Copy)]
+// Any move with Shift held extends the selection instead.
+fn selection_move_from(event: KeyEvent) -> Result<Move, String> {
+    if event.modifiers == KeyModifiers::SHIFT {
+        Move::try_from(KeyEvent {
+            modifiers: KeyModifiers::NONE,
+            ..event
+        })
+    } else {
+        Err(format!("Unsupported modifier {:?}", event.modifiers))
+    }
+}
+
+#[derive(Clone)]
 pub enum Edit {
     Insert(char),
+    InsertText(String),
     InsertNewline,
     Delete,
     DeleteBackward,
+    Indent,
+    Dedent,
+    Undo,
+    Redo,
 }
 impl TryFrom<KeyEvent> for Edit {
     type Error = String;
@@ -62,10 +79,13 @@ impl TryFrom<KeyEvent> for Edit {
             (Char(character), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
                 Ok(Self::Insert(character))
             }
-            (Tab, KeyModifiers::NONE) => Ok(Self::Insert('\t')),
+            (Tab, KeyModifiers::NONE) => Ok(Self::Indent),
+            (BackTab, KeyModifiers::SHIFT) => Ok(Self::Dedent),
             (Enter, KeyModifiers::NONE) => Ok(Self::InsertNewline),
             (Backspace, KeyModifiers::NONE) => Ok(Self::DeleteBackward),
             (Delete, KeyModifiers::NONE) => Ok(Self::Delete),
+            (Char('z'), KeyModifiers::CONTROL) => Ok(Self::Undo),
+            (Char('y'), KeyModifiers::CONTROL) => Ok(Self::Redo),
             _ => Err(format!(
                 "Unsupported key code {:?} with modifiers {:?}",
                 event.code, event.modifiers
@@ -77,6 +97,26 @@ impl TryFrom<KeyEvent> for Edit {
 #[derive(Clone, Copy)]
 pub enum System {
     Save,
+    Search,
+    Replace,
+    CycleSearchMode,
+    ToggleSoftWrap,
+    ConvertLineEndings,
+    ReopenWithEncoding,
+    FocusGained,
+    NextBuffer,
+    PreviousBuffer,
+    PickBuffer,
+    FindFile,
+    // Switches to the tab with the given index.
+    SelectTab(usize),
+    SplitHorizontally,
+    SplitVertically,
+    CloseWindow,
+    FocusNextWindow,
+    Cut,
+    Copy,
+    Paste,
     Resize(Size),
     Quit,
     Dismiss,
@@ -93,8 +133,34 @@ impl TryFrom<KeyEvent> for System {
             match code {
                 Char('q') => Ok(Self::Quit),
                 Char('s') => Ok(Self::Save),
+                Char('f') => Ok(Self::Search),
+                Char('r') => Ok(Self::Replace),
+                Char('t') => Ok(Self::CycleSearchMode),
+                Char('x') => Ok(Self::Cut),
+                Char('c') => Ok(Self::Copy),
+                Char('v') => Ok(Self::Paste),
+                Char('l') => Ok(Self::ConvertLineEndings),
+                Char('b') => Ok(Self::PickBuffer),
+                Char('p') => Ok(Self::FindFile),
+                PageDown => Ok(Self::NextBuffer),
+                PageUp => Ok(Self::PreviousBuffer),
                 _ => Err(format!("Unsupported CONTROL+{code:?} combination")),
             }
+        } else if modifiers == KeyModifiers::ALT {
+            match code {
+                Char('z') => Ok(Self::ToggleSoftWrap),
+                Char('e') => Ok(Self::ReopenWithEncoding),
+                Char('s') => Ok(Self::SplitHorizontally),
+                Char('v') => Ok(Self::SplitVertically),
+                Char('w') => Ok(Self::CloseWindow),
+                Char('o') => Ok(Self::FocusNextWindow),
+                Char(digit @ '1'..='9') => digit
+                    .to_digit(10)
+                    .and_then(|number| usize::try_from(number).ok())
+                    .map(|number| Self::SelectTab(number.saturating_sub(1)))
+                    .ok_or_else(|| format!("Unsupported ALT+{code:?} combination")),
+                _ => Err(format!("Unsupported ALT+{code:?} combination")),
+            }
         } else if modifiers == KeyModifiers::NONE && matches!(code, KeyCode::Esc) {
             Ok(Self::Dismiss)
         } else {
@@ -105,9 +171,10 @@ impl TryFrom<KeyEvent> for System {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub enum Command {
     Move(Move),
+    Select(Move),
     Edit(Edit),
     System(System),
 }
@@ -121,8 +188,11 @@ impl TryFrom<Event> for Command {
             Event::Key(key_event) => Edit::try_from(key_event)
                 .map(Command::Edit)
                 .or_else(|_| Move::try_from(key_event).map(Command::Move))
+                .or_else(|_| selection_move_from(key_event).map(Command::Select))
                 .or_else(|_| System::try_from(key_event).map(Command::System))
                 .map_err(|_err| format!("Event not supported: {key_event:?}")),
+            Event::Paste(text) => Ok(Self::Edit(Edit::InsertText(text))),
+            Event::FocusGained => Ok(Self::System(System::FocusGained)),
             Event::Resize(width_u16, height_u16) => Ok(Self::System(System::Resize(Size {
                 height: height_u16 as usize,
                 width: width_u16 as usize,
@@ -1,6 +1,9 @@
 use std::{cmp::min, io::Error};
 
-use super::{command::Edit, Line, Size, Terminal, UIComponent};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use super::{command::Edit, Line, Position, Size, TabStops, Terminal, UIComponent};
 
 #[derive(Default)]
 pub struct CommandBar {
@@ -14,7 +17,14 @@ impl CommandBar {
     pub fn handle_edit_command(&mut self, command: Edit) {
         match command {
             Edit::Insert(character) => self.value.append_char(character),
-            Edit::Delete | Edit::InsertNewline=> {}
+            // The prompt is a single line, so only the first line of pasted text is used.
+            Edit::InsertText(text) => {
+                if let Some(first_line) = text.lines().next() {
+                    self.value.append_str(first_line);
+                }
+            }
+            Edit::Indent => self.value.append_char('\t'),
+            Edit::Delete | Edit::InsertNewline | Edit::Dedent | Edit::Undo | Edit::Redo => {}
             Edit::DeleteBackward => self.value.delete_last(),
         }
         self.set_needs_redraw(true);
@@ -31,6 +41,11 @@ impl CommandBar {
     }
     pub fn set_prompt(&mut self, prompt: &str) {
         self.prompt = prompt.to_string();
+        self.set_needs_redraw(true);
+    }
+    pub fn clear_value(&mut self) {
+        self.value = Line::default();
+        self.set_needs_redraw(true);
     }
 }
 
@@ -44,20 +59,27 @@ impl UIComponent for CommandBar {
     fn set_size(&mut self, size: Size) {
         self.size = size;
     }
-    fn draw(&mut self, origin: usize) -> Result<(), Error> {
+    fn draw(&mut self, origin: Position) -> Result<(), Error> {
         let area_for_value = self.size.width.saturating_sub(self.prompt.len()); //this is how much space there is between the right side of the prompt and the edge of the bar
-        let value_end = self.value.width(); // we always want to show the left part of the value, therefore the end of the visible range we try to access will be equal to the full width
+        let value_end = self.value.width(TabStops::default()); // we always want to show the left part of the value, therefore the end of the visible range we try to access will be equal to the full width
         let value_start = value_end.saturating_sub(area_for_value); //This should give us the start for the grapheme subrange we want to print out.
         let message = format!(
             "{}{}",
             self.prompt,
-            self.value.get_visible_graphemes(value_start..value_end)
+            self.value
+                .get_visible_graphemes(value_start..value_end, TabStops::default())
         );
-        let to_print = if message.len() <= self.size.width {
-            message
-        } else {
-            String::new()
-        };
-        Terminal::print_row(origin, &to_print)
+        // A prompt that is too long for the bar is cut off rather than hidden.
+        let mut remaining_width = self.size.width;
+        let to_print: String = message
+            .graphemes(true)
+            .take_while(|grapheme| {
+                let width = grapheme.width();
+                let fits = width <= remaining_width;
+                remaining_width = remaining_width.saturating_sub(width);
+                fits
+            })
+            .collect();
+        Terminal::print_row(origin.row, &to_print)
     }
 }
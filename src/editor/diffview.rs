@@ -0,0 +1,112 @@
+use std::io::Error;
+
+use unicode_width::UnicodeWidthChar;
+
+use super::{
+    AnnotatedString, AnnotationType, DiffKind, DiffLine, Position, Size, Terminal, UIComponent,
+};
+
+const HEADER: &str = "Changes on disk (- only here, + only on disk):";
+// Tabs would move the caret unpredictably, so they are shown as this many spaces.
+const TAB_WIDTH: usize = 4;
+
+/// Shows the changes between a buffer and its file on disk, one line of the diff per row.
+#[derive(Default)]
+pub struct DiffView {
+    lines: Vec<DiffLine>,
+    scroll_offset: usize,
+    needs_redraw: bool,
+    size: Size,
+}
+
+impl DiffView {
+    pub fn show(&mut self, lines: Vec<DiffLine>) {
+        self.lines = lines;
+        self.scroll_offset = 0;
+        self.set_needs_redraw(true);
+    }
+    pub fn scroll_up(&mut self, step: usize) {
+        self.scroll_offset = self.scroll_offset.saturating_sub(step);
+        self.set_needs_redraw(true);
+    }
+    pub fn scroll_down(&mut self, step: usize) {
+        // The header takes up the first row.
+        let max_offset = self
+            .lines
+            .len()
+            .saturating_sub(self.size.height.saturating_sub(1));
+        self.scroll_offset = self.scroll_offset.saturating_add(step).min(max_offset);
+        self.set_needs_redraw(true);
+    }
+    pub const fn page_size(&self) -> usize {
+        self.size.height.saturating_sub(1)
+    }
+
+    fn build_row(&self, line: &DiffLine) -> AnnotatedString {
+        let (sign, annotation_type) = match line.kind {
+            DiffKind::Unchanged => (" ", None),
+            DiffKind::Removed => ("-", Some(AnnotationType::Removed)),
+            DiffKind::Added => ("+", Some(AnnotationType::Added)),
+            DiffKind::Skipped => {
+                let mut row = AnnotatedString::default();
+                row.push("      ...", Some(AnnotationType::Comment));
+                return row;
+            }
+        };
+        let prefix = format!("{sign}{:>5} ", line.line_index.saturating_add(1));
+        let mut text = String::new();
+        let mut remaining_width = self.size.width.saturating_sub(prefix.len());
+        for character in line.text.chars() {
+            let expanded = if character == '\t' {
+                " ".repeat(TAB_WIDTH)
+            } else {
+                character.to_string()
+            };
+            let width = if character == '\t' {
+                TAB_WIDTH
+            } else {
+                character.width().unwrap_or_default()
+            };
+            if width > remaining_width {
+                break;
+            }
+            remaining_width = remaining_width.saturating_sub(width);
+            text.push_str(&expanded);
+        }
+        let mut row = AnnotatedString::default();
+        row.push(&prefix, annotation_type);
+        row.push(&text, annotation_type);
+        row
+    }
+}
+
+impl UIComponent for DiffView {
+    fn set_needs_redraw(&mut self, value: bool) {
+        self.needs_redraw = value;
+    }
+    fn needs_redraw(&self) -> bool {
+        self.needs_redraw
+    }
+    fn set_size(&mut self, size: Size) {
+        self.size = size;
+        self.scroll_down(0);
+    }
+    fn draw(&mut self, origin: Position) -> Result<(), Error> {
+        // The header is plain ASCII, so it can be cut off at any byte.
+        let header = HEADER.get(..self.size.width).unwrap_or(HEADER);
+        Terminal::print_row_part(origin, self.size.width, header)?;
+        for row in 1..self.size.height {
+            let row_origin = Position {
+                row: origin.row.saturating_add(row),
+                col: origin.col,
+            };
+            let index = self.scroll_offset.saturating_add(row).saturating_sub(1);
+            if let Some(line) = self.lines.get(index) {
+                Terminal::print_annotated_row(row_origin, self.size.width, &self.build_row(line))?;
+            } else {
+                Terminal::print_row_part(row_origin, self.size.width, "")?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,9 +1,16 @@
+use super::{Encoding, FileType, Indentation, LineEnding};
+
 #[derive(Default, Eq, PartialEq, Debug)]
 pub struct DocumentStatus {
     pub total_lines: usize,
     pub current_line_index: usize,
     pub is_modified: bool,
     pub file_name: String,
+    pub file_type: FileType,
+    pub indentation: Indentation,
+    pub encoding: Encoding,
+    pub line_ending: LineEnding,
+    pub has_final_newline: bool,
 }
 
 impl DocumentStatus {
@@ -24,4 +31,20 @@ impl DocumentStatus {
             self.total_lines
         )
     }
+    pub fn file_type_to_string(&self) -> String {
+        self.file_type.to_string()
+    }
+    pub fn encoding_to_string(&self) -> String {
+        self.encoding.to_string()
+    }
+    pub fn line_ending_to_string(&self) -> String {
+        if self.has_final_newline {
+            self.line_ending.to_string()
+        } else {
+            format!("{} (no final newline)", self.line_ending)
+        }
+    }
+    pub fn indentation_to_string(&self) -> String {
+        self.indentation.to_string()
+    }
 }
@@ -0,0 +1,201 @@
+use std::fmt::{self, Display};
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16_LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16_BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// The character encoding of a file on disk.
+#[derive(Copy, Clone, Default, Eq, PartialEq, Debug)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    // Maps every byte to the character with the same code point, so any file can be
+    // decoded as Latin-1 and written back unchanged.
+    Latin1,
+}
+
+impl Encoding {
+    pub const NAMES: &'static str = "utf-8, utf-8-bom, utf-16le, utf-16be, latin-1";
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(Self::Utf8),
+            "utf-8-bom" | "utf8-bom" => Some(Self::Utf8Bom),
+            "utf-16le" | "utf16le" => Some(Self::Utf16Le),
+            "utf-16be" | "utf16be" => Some(Self::Utf16Be),
+            "latin-1" | "latin1" | "iso-8859-1" => Some(Self::Latin1),
+            _ => None,
+        }
+    }
+
+    /// Detects the encoding of `bytes` from its byte order mark and whether it is valid UTF-8,
+    /// and decodes it. Anything that can't be decoded otherwise falls back to Latin-1.
+    pub fn detect_and_decode(bytes: &[u8]) -> (Self, String) {
+        let candidate = if bytes.starts_with(UTF8_BOM) {
+            Self::Utf8Bom
+        } else if bytes.starts_with(UTF16_LE_BOM) {
+            Self::Utf16Le
+        } else if bytes.starts_with(UTF16_BE_BOM) {
+            Self::Utf16Be
+        } else {
+            Self::Utf8
+        };
+        candidate.decode(bytes).map_or_else(
+            || (Self::Latin1, Self::decode_latin1(bytes)),
+            |text| (candidate, text),
+        )
+    }
+
+    /// Decodes `bytes`, skipping the byte order mark if there is one.
+    /// Returns `None` if `bytes` isn't valid in this encoding.
+    pub fn decode(self, bytes: &[u8]) -> Option<String> {
+        match self {
+            Self::Utf8 | Self::Utf8Bom => {
+                let bytes = bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes);
+                String::from_utf8(bytes.to_vec()).ok()
+            }
+            Self::Utf16Le => Self::decode_utf16(
+                bytes.strip_prefix(UTF16_LE_BOM).unwrap_or(bytes),
+                u16::from_le_bytes,
+            ),
+            Self::Utf16Be => Self::decode_utf16(
+                bytes.strip_prefix(UTF16_BE_BOM).unwrap_or(bytes),
+                u16::from_be_bytes,
+            ),
+            Self::Latin1 => Some(Self::decode_latin1(bytes)),
+        }
+    }
+
+    /// Encodes `text`, starting with a byte order mark where the encoding has one.
+    /// Returns `None` if `text` contains characters this encoding can't represent.
+    pub fn encode(self, text: &str) -> Option<Vec<u8>> {
+        match self {
+            Self::Utf8 => Some(text.as_bytes().to_vec()),
+            Self::Utf8Bom => Some([UTF8_BOM, text.as_bytes()].concat()),
+            Self::Utf16Le => Some(
+                UTF16_LE_BOM
+                    .iter()
+                    .copied()
+                    .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+                    .collect(),
+            ),
+            Self::Utf16Be => Some(
+                UTF16_BE_BOM
+                    .iter()
+                    .copied()
+                    .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+                    .collect(),
+            ),
+            Self::Latin1 => text
+                .chars()
+                .map(|ch| u8::try_from(u32::from(ch)).ok())
+                .collect(),
+        }
+    }
+
+    /// Checks whether every character of `text` can be represented in this encoding.
+    pub fn can_encode(self, text: &str) -> bool {
+        match self {
+            Self::Latin1 => text.chars().all(|ch| u32::from(ch) <= 0xFF),
+            Self::Utf8 | Self::Utf8Bom | Self::Utf16Le | Self::Utf16Be => true,
+        }
+    }
+
+    fn decode_utf16(bytes: &[u8], to_unit: fn([u8; 2]) -> u16) -> Option<String> {
+        let chunks = bytes.chunks_exact(2);
+        if !chunks.remainder().is_empty() {
+            return None;
+        }
+        let units: Vec<u16> = chunks.map(|chunk| to_unit([chunk[0], chunk[1]])).collect();
+        String::from_utf16(&units).ok()
+    }
+
+    fn decode_latin1(bytes: &[u8]) -> String {
+        bytes.iter().copied().map(char::from).collect()
+    }
+}
+
+impl Display for Encoding {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Utf8 => "UTF-8",
+            Self::Utf8Bom => "UTF-8 BOM",
+            Self::Utf16Le => "UTF-16 LE",
+            Self::Utf16Be => "UTF-16 BE",
+            Self::Latin1 => "Latin-1",
+        };
+        write!(formatter, "{name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Encoding;
+
+    const TEXT: &str = "Grüße, 世界! 🦀\n";
+
+    #[test]
+    fn unicode_encodings_round_trip() {
+        for encoding in [
+            Encoding::Utf8,
+            Encoding::Utf8Bom,
+            Encoding::Utf16Le,
+            Encoding::Utf16Be,
+        ] {
+            let bytes = encoding.encode(TEXT).unwrap();
+            assert_eq!(
+                Encoding::detect_and_decode(&bytes),
+                (encoding, TEXT.to_string())
+            );
+            assert_eq!(encoding.decode(&bytes).as_deref(), Some(TEXT));
+        }
+    }
+
+    #[test]
+    fn byte_order_marks_are_written_and_skipped() {
+        assert_eq!(
+            Encoding::Utf8Bom.encode("a").unwrap(),
+            [0xEF, 0xBB, 0xBF, b'a']
+        );
+        assert_eq!(
+            Encoding::Utf16Le.encode("a").unwrap(),
+            [0xFF, 0xFE, b'a', 0]
+        );
+        assert_eq!(
+            Encoding::Utf16Be.encode("a").unwrap(),
+            [0xFE, 0xFF, 0, b'a']
+        );
+        assert_eq!(
+            Encoding::Utf8.decode(&[0xEF, 0xBB, 0xBF, b'a']).as_deref(),
+            Some("a")
+        );
+    }
+
+    #[test]
+    fn invalid_utf8_falls_back_to_latin1_and_round_trips() {
+        let bytes = [b'c', b'a', b'f', 0xE9, b'\n', 0xFF];
+        let (encoding, text) = Encoding::detect_and_decode(&bytes);
+        assert_eq!(encoding, Encoding::Latin1);
+        assert_eq!(text, "caf\u{e9}\n\u{ff}");
+        assert_eq!(encoding.encode(&text).unwrap(), bytes);
+    }
+
+    #[test]
+    fn truncated_utf16_falls_back_to_latin1() {
+        let bytes = [0xFF, 0xFE, b'a'];
+        assert_eq!(Encoding::Utf16Le.decode(&bytes), None);
+        assert_eq!(Encoding::detect_and_decode(&bytes).0, Encoding::Latin1);
+    }
+
+    #[test]
+    fn latin1_cannot_encode_characters_beyond_one_byte() {
+        assert_eq!(Encoding::Latin1.encode("é").unwrap(), [0xE9]);
+        assert_eq!(Encoding::Latin1.encode("世"), None);
+        assert!(Encoding::Latin1.can_encode("café"));
+        assert!(!Encoding::Latin1.can_encode("世"));
+        assert!(Encoding::Utf16Le.can_encode("世"));
+    }
+}
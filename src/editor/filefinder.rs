@@ -0,0 +1,211 @@
+use std::{
+    cmp::Reverse,
+    fs,
+    io::Error,
+    path::{Path, PathBuf},
+};
+
+use unicode_width::UnicodeWidthChar;
+
+use super::{AnnotatedString, AnnotationType, Position, Size, Terminal, UIComponent};
+mod fuzzy;
+mod gitignore;
+use gitignore::Gitignore;
+
+// Walking stops after this many files, so that opening the finder in a huge tree stays quick.
+const MAX_FILES: usize = 20_000;
+const SELECTION_MARKER: &str = "> ";
+
+struct Match {
+    path_index: usize,
+    score: usize,
+    // The indices of the characters of the path that matched the query.
+    matched: Vec<usize>,
+}
+
+/// Lists the files below the working directory that match a fuzzy query, best match first.
+#[derive(Default)]
+pub struct FileFinder {
+    root: PathBuf,
+    // Relative to the root, separated by slashes.
+    paths: Vec<String>,
+    matches: Vec<Match>,
+    selected_index: usize,
+    scroll_offset: usize,
+    needs_redraw: bool,
+    size: Size,
+}
+
+impl FileFinder {
+    /// Collects the files below `root` that no .gitignore file ignores, and lists all of them.
+    pub fn open(&mut self, root: &Path) -> Result<(), Error> {
+        let mut paths = Vec::new();
+        walk(root, Path::new(""), &Gitignore::default(), &mut paths)?;
+        paths.sort();
+        self.root = root.to_path_buf();
+        self.paths = paths;
+        self.update_query("");
+        Ok(())
+    }
+    pub fn update_query(&mut self, query: &str) {
+        let mut matches: Vec<Match> = self
+            .paths
+            .iter()
+            .enumerate()
+            .filter_map(|(path_index, path)| {
+                fuzzy::score(query, path).map(|(score, matched)| Match {
+                    path_index,
+                    score,
+                    matched,
+                })
+            })
+            .collect();
+        // Among equally good matches, shorter paths come first.
+        matches.sort_by_key(|candidate| {
+            (
+                Reverse(candidate.score),
+                self.paths.get(candidate.path_index).map_or(0, String::len),
+            )
+        });
+        self.matches = matches;
+        self.selected_index = 0;
+        self.scroll_offset = 0;
+        self.set_needs_redraw(true);
+    }
+    pub fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+    pub fn file_count(&self) -> usize {
+        self.paths.len()
+    }
+    /// Returns the path of the selected file, or `None` if nothing matches.
+    pub fn selected_path(&self) -> Option<PathBuf> {
+        self.matches
+            .get(self.selected_index)
+            .and_then(|selected| self.paths.get(selected.path_index))
+            .map(|path| self.root.join(path))
+    }
+
+    pub fn select_next(&mut self, step: usize) {
+        self.selected_index = self
+            .selected_index
+            .saturating_add(step)
+            .min(self.matches.len().saturating_sub(1));
+        self.scroll_selection_into_view();
+    }
+    pub fn select_previous(&mut self, step: usize) {
+        self.selected_index = self.selected_index.saturating_sub(step);
+        self.scroll_selection_into_view();
+    }
+    pub const fn page_size(&self) -> usize {
+        self.size.height
+    }
+    fn scroll_selection_into_view(&mut self) {
+        let height = self.size.height.max(1);
+        if self.selected_index < self.scroll_offset {
+            self.scroll_offset = self.selected_index;
+        } else if self.selected_index >= self.scroll_offset.saturating_add(height) {
+            self.scroll_offset = self.selected_index.saturating_sub(height).saturating_add(1);
+        }
+        self.set_needs_redraw(true);
+    }
+
+    fn build_row(&self, candidate: &Match, is_selected: bool) -> AnnotatedString {
+        let mut row = AnnotatedString::default();
+        let path = self
+            .paths
+            .get(candidate.path_index)
+            .map_or("", String::as_str);
+        let (marker, annotation_type) = if is_selected {
+            (SELECTION_MARKER, Some(AnnotationType::Selection))
+        } else {
+            ("  ", None)
+        };
+        row.push(marker, None);
+        let mut remaining_width = self.size.width.saturating_sub(SELECTION_MARKER.len());
+        for (index, character) in path.chars().enumerate() {
+            let width = character.width().unwrap_or_default();
+            if width > remaining_width {
+                break;
+            }
+            remaining_width = remaining_width.saturating_sub(width);
+            let annotation_type = if candidate.matched.contains(&index) && !is_selected {
+                Some(AnnotationType::Match)
+            } else {
+                annotation_type
+            };
+            row.push(&character.to_string(), annotation_type);
+        }
+        row
+    }
+}
+
+// Adds the files in `dir`, given relative to `root`, and in all directories below it to `paths`.
+fn walk(
+    root: &Path,
+    dir: &Path,
+    gitignore: &Gitignore,
+    paths: &mut Vec<String>,
+) -> Result<(), Error> {
+    let absolute_dir = root.join(dir);
+    let mut gitignore = gitignore.clone();
+    if let Ok(contents) = fs::read_to_string(absolute_dir.join(".gitignore")) {
+        gitignore.add_file(dir, &contents);
+    }
+    for entry in fs::read_dir(&absolute_dir)?.filter_map(Result::ok) {
+        if paths.len() >= MAX_FILES {
+            break;
+        }
+        let name = entry.file_name();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let path = dir.join(&name);
+        if name == ".git" || gitignore.is_ignored(&path, file_type.is_dir()) {
+            continue;
+        }
+        // Symbolic links to directories aren't followed, so that a cycle can't trap the walk.
+        if file_type.is_dir() {
+            // A directory that can't be read just doesn't contribute any files.
+            let _ = walk(root, &path, &gitignore, paths);
+        } else if file_type.is_file() || absolute_dir.join(&name).is_file() {
+            let components: Vec<_> = path
+                .iter()
+                .map(|component| component.to_string_lossy())
+                .collect();
+            paths.push(components.join("/"));
+        }
+    }
+    Ok(())
+}
+
+impl UIComponent for FileFinder {
+    fn set_needs_redraw(&mut self, value: bool) {
+        self.needs_redraw = value;
+    }
+    fn needs_redraw(&self) -> bool {
+        self.needs_redraw
+    }
+    fn set_size(&mut self, size: Size) {
+        self.size = size;
+        self.scroll_selection_into_view();
+    }
+    fn draw(&mut self, origin: Position) -> Result<(), Error> {
+        for row in 0..self.size.height {
+            let row_origin = Position {
+                row: origin.row.saturating_add(row),
+                col: origin.col,
+            };
+            let index = self.scroll_offset.saturating_add(row);
+            if let Some(candidate) = self.matches.get(index) {
+                let annotated_row = self.build_row(candidate, index == self.selected_index);
+                Terminal::print_annotated_row(row_origin, self.size.width, &annotated_row)?;
+            } else if index == 0 {
+                Terminal::print_row_part(row_origin, self.size.width, "  No matching files.")?;
+            } else {
+                Terminal::print_row_part(row_origin, self.size.width, "")?;
+            }
+        }
+        Ok(())
+    }
+}
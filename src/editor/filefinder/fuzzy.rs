@@ -0,0 +1,121 @@
+// Matching a character right after the previous match.
+const CONSECUTIVE_BONUS: usize = 5;
+// Matching the first character of a path component.
+const COMPONENT_START_BONUS: usize = 8;
+// Matching the first character of a word within a component, like the "b" in "foo_bar".
+const WORD_START_BONUS: usize = 6;
+// Matching with the same case as typed.
+const EXACT_CASE_BONUS: usize = 1;
+const MATCH_SCORE: usize = 10;
+
+/// Scores how well `query` matches `candidate`, ignoring case. All characters of the query
+/// have to appear in the candidate in order. Returns the score, where higher is better, and
+/// the indices of the matched characters, or `None` if the query doesn't match.
+///
+/// Characters are matched from the back, so that matches in the file name are preferred over
+/// matches in the directories leading up to it.
+pub fn score(query: &str, candidate: &str) -> Option<(usize, Vec<usize>)> {
+    let candidate: Vec<char> = candidate.chars().collect();
+    let mut matched = Vec::new();
+    let mut remaining = candidate.len();
+    for wanted in query
+        .chars()
+        .rev()
+        .filter(|character| !character.is_whitespace())
+    {
+        let index = candidate
+            .get(..remaining)?
+            .iter()
+            .rposition(|&character| eq_ignore_case(character, wanted))?;
+        matched.push(index);
+        remaining = index;
+    }
+    matched.reverse();
+
+    let mut score: usize = 0;
+    let mut previous: Option<usize> = None;
+    for &index in &matched {
+        score = score.saturating_add(MATCH_SCORE);
+        let before = index
+            .checked_sub(1)
+            .and_then(|before| candidate.get(before));
+        let current = candidate.get(index).copied().unwrap_or_default();
+        score = score.saturating_add(match before {
+            None | Some('/') => COMPONENT_START_BONUS,
+            Some(&before) if is_word_start(before, current) => WORD_START_BONUS,
+            Some(_) => 0,
+        });
+        if previous.is_some_and(|previous| previous.saturating_add(1) == index) {
+            score = score.saturating_add(CONSECUTIVE_BONUS);
+        }
+        if query.contains(current) {
+            score = score.saturating_add(EXACT_CASE_BONUS);
+        }
+        previous = Some(index);
+    }
+    Some((score, matched))
+}
+
+fn eq_ignore_case(left: char, right: char) -> bool {
+    left == right || left.to_lowercase().eq(right.to_lowercase())
+}
+
+fn is_word_start(before: char, current: char) -> bool {
+    matches!(before, '_' | '-' | '.' | ' ') || (before.is_lowercase() && current.is_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::score;
+
+    fn points(query: &str, candidate: &str) -> usize {
+        score(query, candidate).map_or(0, |(score, _)| score)
+    }
+
+    #[test]
+    fn all_characters_must_match_in_order() {
+        assert!(score("mr", "src/main.rs").is_some());
+        assert!(score("nm", "src/main.rs").is_none());
+        assert!(score("mainx", "src/main.rs").is_none());
+        assert_eq!(score("", "src/main.rs"), Some((0, Vec::new())));
+    }
+
+    #[test]
+    fn returns_the_matched_indices() {
+        assert_eq!(
+            score("mr", "src/main.rs").map(|(_, indices)| indices),
+            Some(vec![4, 9])
+        );
+        // Matching from the back prefers the file name over the directories.
+        assert_eq!(
+            score("m", "main/main.rs").map(|(_, indices)| indices),
+            Some(vec![5])
+        );
+    }
+
+    #[test]
+    fn ignores_case_and_whitespace() {
+        assert!(score("MAIN", "src/main.rs").is_some());
+        assert_eq!(
+            points("ma in", "src/main.rs"),
+            points("main", "src/main.rs")
+        );
+    }
+
+    #[test]
+    fn prefers_exact_case() {
+        assert!(points("M", "Main.rs") > points("m", "Main.rs"));
+    }
+
+    #[test]
+    fn prefers_word_and_component_starts() {
+        assert!(points("fb", "src/foo_bar.rs") > points("fb", "src/fab.rs"));
+        assert!(points("fb", "src/fooBar.rs") > points("fb", "src/fab.rs"));
+        assert!(points("b", "src/bar.rs") > points("b", "src/abc.rs"));
+    }
+
+    #[test]
+    fn prefers_consecutive_matches() {
+        assert!(points("abc", "xabcx") > points("abc", "xaxbxcx"));
+    }
+}
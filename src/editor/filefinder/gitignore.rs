@@ -0,0 +1,221 @@
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use regex::Regex;
+
+struct Rule {
+    // The directory of the .gitignore file the rule comes from, relative to the root.
+    base: PathBuf,
+    regex: Regex,
+    is_negated: bool,
+    is_dir_only: bool,
+    // Patterns containing a slash match against the whole path below `base`, others against the name.
+    is_anchored: bool,
+}
+
+impl Rule {
+    fn parse(base: &Path, line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (is_negated, pattern) = match line.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (is_dir_only, pattern) = match pattern.strip_suffix('/') {
+            Some(pattern) => (true, pattern),
+            None => (false, pattern),
+        };
+        let is_anchored = pattern.contains('/');
+        let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+        let regex = Regex::new(&format!("^{}$", glob_to_regex(pattern))).ok()?;
+        Some(Self {
+            base: base.to_path_buf(),
+            regex,
+            is_negated,
+            is_dir_only,
+            is_anchored,
+        })
+    }
+    fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        if self.is_dir_only && !is_dir {
+            return false;
+        }
+        let Ok(relative) = path.strip_prefix(&self.base) else {
+            return false;
+        };
+        if self.is_anchored {
+            let relative = relative
+                .iter()
+                .map(|component| component.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            self.regex.is_match(&relative)
+        } else {
+            relative
+                .file_name()
+                .is_some_and(|name| self.regex.is_match(&name.to_string_lossy()))
+        }
+    }
+}
+
+// Translates a gitignore glob into a regular expression.
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::new();
+    let mut chars = glob.chars().peekable();
+    while let Some(character) = chars.next() {
+        match character {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    // "**/" matches any number of leading directories, including none.
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => {
+                regex.push('[');
+                if chars.peek() == Some(&'!') {
+                    chars.next();
+                    regex.push('^');
+                }
+                for inner in chars.by_ref() {
+                    if inner == ']' {
+                        break;
+                    }
+                    if inner == '\\' || inner == '[' {
+                        regex.push('\\');
+                    }
+                    regex.push(inner);
+                }
+                regex.push(']');
+            }
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    regex.push_str(&regex::escape(&escaped.to_string()));
+                }
+            }
+            _ => regex.push_str(&regex::escape(&character.to_string())),
+        }
+    }
+    regex
+}
+
+/// The rules of all .gitignore files that apply to a directory, outermost first.
+#[derive(Clone, Default)]
+pub struct Gitignore {
+    rules: Vec<Rc<Rule>>,
+}
+
+impl Gitignore {
+    /// Adds the rules of the .gitignore file in `dir`, given relative to the root.
+    pub fn add_file(&mut self, dir: &Path, contents: &str) {
+        self.rules.extend(
+            contents
+                .lines()
+                .filter_map(|line| Rule::parse(dir, line))
+                .map(Rc::new),
+        );
+    }
+    /// Checks whether `path`, given relative to the root, is ignored. The last matching rule wins.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(path, is_dir))
+            .is_some_and(|rule| !rule.is_negated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Gitignore;
+    use std::path::Path;
+
+    fn gitignore(contents: &str) -> Gitignore {
+        let mut gitignore = Gitignore::default();
+        gitignore.add_file(Path::new(""), contents);
+        gitignore
+    }
+
+    fn is_ignored(gitignore: &Gitignore, path: &str) -> bool {
+        gitignore.is_ignored(Path::new(path), false)
+    }
+
+    #[test]
+    fn patterns_without_slash_match_names_anywhere() {
+        let gitignore = gitignore("*.log\n");
+        assert!(is_ignored(&gitignore, "debug.log"));
+        assert!(is_ignored(&gitignore, "a/b/debug.log"));
+        assert!(!is_ignored(&gitignore, "debug.log.txt"));
+    }
+
+    #[test]
+    fn patterns_with_slash_are_anchored() {
+        let gitignore = gitignore("/build\ndoc/*.txt\n");
+        assert!(is_ignored(&gitignore, "build"));
+        assert!(!is_ignored(&gitignore, "src/build"));
+        assert!(is_ignored(&gitignore, "doc/notes.txt"));
+        assert!(!is_ignored(&gitignore, "doc/sub/notes.txt"));
+        assert!(!is_ignored(&gitignore, "src/doc/notes.txt"));
+    }
+
+    #[test]
+    fn double_stars_match_any_number_of_directories() {
+        let gitignore = gitignore("**/target\na/**/b\nlogs/**\n");
+        assert!(is_ignored(&gitignore, "target"));
+        assert!(is_ignored(&gitignore, "x/y/target"));
+        assert!(is_ignored(&gitignore, "a/b"));
+        assert!(is_ignored(&gitignore, "a/x/y/b"));
+        assert!(!is_ignored(&gitignore, "c/a/b"));
+        assert!(is_ignored(&gitignore, "logs/today/app.txt"));
+    }
+
+    #[test]
+    fn trailing_slash_matches_directories_only() {
+        let gitignore = gitignore("out/\n");
+        assert!(gitignore.is_ignored(Path::new("out"), true));
+        assert!(gitignore.is_ignored(Path::new("src/out"), true));
+        assert!(!gitignore.is_ignored(Path::new("out"), false));
+    }
+
+    #[test]
+    fn the_last_matching_rule_wins() {
+        let gitignore = gitignore("*.log\n!keep.log\n");
+        assert!(is_ignored(&gitignore, "debug.log"));
+        assert!(!is_ignored(&gitignore, "keep.log"));
+        let gitignore = self::gitignore("!keep.log\n*.log\n");
+        assert!(is_ignored(&gitignore, "keep.log"));
+    }
+
+    #[test]
+    fn nested_files_apply_below_their_directory() {
+        let mut gitignore = gitignore("*.tmp\n");
+        gitignore.add_file(Path::new("sub"), "!keep.tmp\n/local\n");
+        assert!(is_ignored(&gitignore, "keep.tmp"));
+        assert!(!is_ignored(&gitignore, "sub/keep.tmp"));
+        assert!(is_ignored(&gitignore, "sub/other.tmp"));
+        assert!(is_ignored(&gitignore, "sub/local"));
+        assert!(!is_ignored(&gitignore, "local"));
+        assert!(!is_ignored(&gitignore, "sub/deeper/local"));
+    }
+
+    #[test]
+    fn comments_escapes_and_classes() {
+        let gitignore = gitignore("# comment\n\\#hash\n\\!bang\nfile[0-9].txt\n[!a]b\n");
+        assert!(!is_ignored(&gitignore, "# comment"));
+        assert!(is_ignored(&gitignore, "#hash"));
+        assert!(is_ignored(&gitignore, "!bang"));
+        assert!(is_ignored(&gitignore, "file7.txt"));
+        assert!(!is_ignored(&gitignore, "filex.txt"));
+        assert!(is_ignored(&gitignore, "cb"));
+        assert!(!is_ignored(&gitignore, "ab"));
+    }
+}
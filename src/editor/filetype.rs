@@ -0,0 +1,59 @@
+use std::{
+    fmt::{self, Display},
+    path::Path,
+};
+
+#[derive(Copy, Clone, Default, Eq, PartialEq, Debug)]
+pub enum FileType {
+    Rust,
+    Markdown,
+    Toml,
+    Json,
+    Shell,
+    Python,
+    Yaml,
+    #[default]
+    Text,
+}
+
+impl FileType {
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("rs") => Self::Rust,
+            Some("md" | "markdown") => Self::Markdown,
+            Some("toml") => Self::Toml,
+            Some("json") => Self::Json,
+            Some("sh" | "bash" | "zsh") => Self::Shell,
+            Some("py") => Self::Python,
+            Some("yaml" | "yml") => Self::Yaml,
+            _ => Self::Text,
+        }
+    }
+
+    /// Returns the characters after which a new line is indented one level deeper.
+    pub const fn indent_triggers(self) -> &'static [char] {
+        match self {
+            Self::Rust | Self::Json | Self::Shell => &['{', '(', '['],
+            Self::Toml => &['['],
+            Self::Python => &[':', '{', '(', '['],
+            Self::Yaml => &[':'],
+            Self::Markdown | Self::Text => &[],
+        }
+    }
+}
+
+impl Display for FileType {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Rust => "Rust",
+            Self::Markdown => "Markdown",
+            Self::Toml => "TOML",
+            Self::Json => "JSON",
+            Self::Shell => "Shell",
+            Self::Python => "Python",
+            Self::Yaml => "YAML",
+            Self::Text => "Text",
+        };
+        write!(formatter, "{name}")
+    }
+}
@@ -0,0 +1,170 @@
+use std::fmt::{self, Display};
+
+use super::Line;
+
+/// What a single level of indentation is made of.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Indentation {
+    Tabs,
+    Spaces(usize),
+}
+
+impl Default for Indentation {
+    fn default() -> Self {
+        Self::Spaces(4)
+    }
+}
+
+impl Indentation {
+    /// Parses `tabs` or a number of spaces.
+    pub fn from_name(name: &str) -> Option<Self> {
+        if name == "tabs" {
+            return Some(Self::Tabs);
+        }
+        name.parse()
+            .ok()
+            .filter(|&width| width > 0)
+            .map(Self::Spaces)
+    }
+
+    /// Returns the text of one indentation level.
+    pub fn unit(self) -> String {
+        match self {
+            Self::Tabs => String::from("\t"),
+            Self::Spaces(width) => " ".repeat(width),
+        }
+    }
+
+    /// Returns the width of one indentation level in columns. Tabs are as wide as a tab stop.
+    pub const fn width(self, tab_width: usize) -> usize {
+        match self {
+            Self::Tabs => tab_width,
+            Self::Spaces(width) => width,
+        }
+    }
+
+    /// Guesses the indentation used by the given lines, or returns `None` if none are indented.
+    /// Tabs win if more lines start with a tab than with spaces. Otherwise, the indent width is
+    /// the most common change in indentation between consecutive space-indented lines.
+    pub fn detect(lines: &[Line]) -> Option<Self> {
+        let mut tab_lines: usize = 0;
+        let mut space_lines: usize = 0;
+        // How often the indentation changed by 1, 2, ... 8 spaces.
+        let mut width_counts = [0_usize; 8];
+        let mut previous_spaces: usize = 0;
+        for line in lines {
+            let text = line.to_string();
+            let content = text.trim_start_matches([' ', '\t']);
+            // Blank lines say nothing about indentation, and neither do the ` * ` lines of block comments.
+            if content.is_empty() || content.starts_with('*') {
+                continue;
+            }
+            if text.starts_with('\t') {
+                tab_lines = tab_lines.saturating_add(1);
+                continue;
+            }
+            let spaces = text.len().saturating_sub(content.len());
+            if spaces > 0 {
+                space_lines = space_lines.saturating_add(1);
+            }
+            if let Some(count) = spaces
+                .abs_diff(previous_spaces)
+                .checked_sub(1)
+                .and_then(|index| width_counts.get_mut(index))
+            {
+                *count = count.saturating_add(1);
+            }
+            previous_spaces = spaces;
+        }
+        if tab_lines == 0 && space_lines == 0 {
+            return None;
+        }
+        if tab_lines > space_lines {
+            return Some(Self::Tabs);
+        }
+        // Ties are resolved towards the smaller width.
+        let (index, _) = width_counts
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, &count)| count > 0)
+            .max_by_key(|(_, &count)| count)?;
+        Some(Self::Spaces(index.saturating_add(1)))
+    }
+}
+
+impl Display for Indentation {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tabs => write!(formatter, "Tabs"),
+            Self::Spaces(width) => write!(formatter, "Spaces: {width}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Indentation, Line};
+
+    fn detect(text: &str) -> Option<Indentation> {
+        let lines: Vec<Line> = text.lines().map(Line::from).collect();
+        Indentation::detect(&lines)
+    }
+
+    #[test]
+    fn detects_tabs() {
+        assert_eq!(
+            detect("fn f() {\n\tif x {\n\t\ty();\n\t}\n}"),
+            Some(Indentation::Tabs)
+        );
+    }
+
+    #[test]
+    fn detects_the_space_width() {
+        assert_eq!(
+            detect("a:\n  b:\n    c: 1\n  d: 2\ne: 3"),
+            Some(Indentation::Spaces(2))
+        );
+        assert_eq!(
+            detect("fn f() {\n    if x {\n        y();\n    }\n}"),
+            Some(Indentation::Spaces(4))
+        );
+    }
+
+    #[test]
+    fn the_most_common_step_wins() {
+        // A single continuation line aligned with 2 extra spaces doesn't outweigh the rest.
+        assert_eq!(
+            detect("a\n    b\n      c\n    d\n        e\n    f\ng"),
+            Some(Indentation::Spaces(4))
+        );
+        // Ties go to the smaller width.
+        assert_eq!(detect("a\n  b\n      c"), Some(Indentation::Spaces(2)));
+    }
+
+    #[test]
+    fn mixed_files_go_with_the_majority() {
+        assert_eq!(detect("a\n\tb\n\tc\n    d\ne"), Some(Indentation::Tabs));
+        assert_eq!(
+            detect("a\n\tb\n    c\n    d\ne"),
+            Some(Indentation::Spaces(4))
+        );
+        // As many tab as space lines isn't enough for tabs.
+        assert_eq!(detect("a\n\tb\n    c\nd"), Some(Indentation::Spaces(4)));
+    }
+
+    #[test]
+    fn ignores_blank_lines_and_block_comment_continuations() {
+        assert_eq!(
+            detect("/**\n * Doc\n */\nfn f() {\n\n  x();\n   \n}"),
+            Some(Indentation::Spaces(2))
+        );
+    }
+
+    #[test]
+    fn returns_none_without_indentation() {
+        assert_eq!(detect(""), None);
+        assert_eq!(detect("a\nb\n\nc"), None);
+        assert_eq!(detect("a\n    \n\t\nb"), None);
+    }
+}
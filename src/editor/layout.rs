@@ -0,0 +1,294 @@
+use std::{io::Error, mem};
+
+use super::{Position, Size, Terminal, UIComponent, View, Window};
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum SplitDirection {
+    // One window above the other.
+    Horizontal,
+    // Windows side by side, divided by a vertical separator.
+    Vertical,
+}
+
+// Windows are the leaves of a tree of splits. They are numbered from left to right.
+enum Node {
+    Window(Box<Window>),
+    Split {
+        direction: SplitDirection,
+        first: Box<Node>,
+        second: Box<Node>,
+    },
+}
+
+impl Default for Node {
+    fn default() -> Self {
+        Self::Window(Box::default())
+    }
+}
+
+impl Node {
+    fn window_count(&self) -> usize {
+        match self {
+            Self::Window(_) => 1,
+            Self::Split { first, second, .. } => {
+                first.window_count().saturating_add(second.window_count())
+            }
+        }
+    }
+    fn windows<'a>(&'a self, windows: &mut Vec<&'a Window>) {
+        match self {
+            Self::Window(window) => windows.push(window),
+            Self::Split { first, second, .. } => {
+                first.windows(windows);
+                second.windows(windows);
+            }
+        }
+    }
+    fn windows_mut<'a>(&'a mut self, windows: &mut Vec<&'a mut Window>) {
+        match self {
+            Self::Window(window) => windows.push(window),
+            Self::Split { first, second, .. } => {
+                first.windows_mut(windows);
+                second.windows_mut(windows);
+            }
+        }
+    }
+    // Splits the window with the given number in two, both showing its view.
+    fn split(&mut self, index: usize, direction: SplitDirection) {
+        match self {
+            Self::Window(window) => {
+                if index == 0 {
+                    let second = Window::new(window.view.duplicate());
+                    let first = mem::take(self);
+                    *self = Self::Split {
+                        direction,
+                        first: Box::new(first),
+                        second: Box::new(Self::Window(Box::new(second))),
+                    };
+                }
+            }
+            Self::Split { first, second, .. } => {
+                let first_count = first.window_count();
+                if index < first_count {
+                    first.split(index, direction);
+                } else {
+                    second.split(index.saturating_sub(first_count), direction);
+                }
+            }
+        }
+    }
+    // Removes the window with the given number, letting its sibling take over its space.
+    fn close(&mut self, index: usize) {
+        let sibling = match self {
+            Self::Window(_) => return,
+            Self::Split { first, second, .. } => {
+                let first_count = first.window_count();
+                if index < first_count {
+                    if !matches!(**first, Self::Window(_)) {
+                        first.close(index);
+                        return;
+                    }
+                    mem::take(&mut **second)
+                } else {
+                    if !matches!(**second, Self::Window(_)) {
+                        second.close(index.saturating_sub(first_count));
+                        return;
+                    }
+                    mem::take(&mut **first)
+                }
+            }
+        };
+        *self = sibling;
+    }
+    // Divides the area at `origin` with the given size among the windows, halving it at each split.
+    // Collects the vertical separators between windows side by side as their top and height.
+    fn place(&mut self, origin: Position, size: Size, separators: &mut Vec<(Position, usize)>) {
+        match self {
+            Self::Window(window) => window.place(origin, size),
+            Self::Split {
+                direction: SplitDirection::Horizontal,
+                first,
+                second,
+            } => {
+                let first_height = size.height.div_ceil(2);
+                first.place(
+                    origin,
+                    Size {
+                        height: first_height,
+                        width: size.width,
+                    },
+                    separators,
+                );
+                second.place(
+                    Position {
+                        row: origin.row.saturating_add(first_height),
+                        col: origin.col,
+                    },
+                    Size {
+                        height: size.height.saturating_sub(first_height),
+                        width: size.width,
+                    },
+                    separators,
+                );
+            }
+            Self::Split {
+                direction: SplitDirection::Vertical,
+                first,
+                second,
+            } => {
+                // One column goes to the separator.
+                let first_width = size.width.saturating_sub(1).div_ceil(2);
+                let separator_col = origin.col.saturating_add(first_width);
+                first.place(
+                    origin,
+                    Size {
+                        height: size.height,
+                        width: first_width,
+                    },
+                    separators,
+                );
+                separators.push((
+                    Position {
+                        row: origin.row,
+                        col: separator_col,
+                    },
+                    size.height,
+                ));
+                second.place(
+                    Position {
+                        row: origin.row,
+                        col: separator_col.saturating_add(1),
+                    },
+                    Size {
+                        height: size.height,
+                        width: size.width.saturating_sub(first_width).saturating_sub(1),
+                    },
+                    separators,
+                );
+            }
+        }
+    }
+}
+
+/// Divides the area above the message bar into windows, one of which is current.
+#[derive(Default)]
+pub struct Layout {
+    root: Node,
+    current_index: usize,
+    size: Size,
+    // The vertical separators between windows side by side, as their top and height.
+    separators: Vec<(Position, usize)>,
+    needs_redraw: bool,
+}
+
+impl Layout {
+    pub fn new(view: View) -> Self {
+        Self {
+            root: Node::Window(Box::new(Window::new(view))),
+            ..Self::default()
+        }
+    }
+    fn windows(&self) -> Vec<&Window> {
+        let mut windows = Vec::new();
+        self.root.windows(&mut windows);
+        windows
+    }
+    fn windows_mut(&mut self) -> Vec<&mut Window> {
+        let mut windows = Vec::new();
+        self.root.windows_mut(&mut windows);
+        windows
+    }
+    // clippy::indexing_slicing: current_index always points to one of the windows, of which there is at least one
+    #[allow(clippy::indexing_slicing)]
+    fn current_window(&self) -> &Window {
+        self.windows()[self.current_index]
+    }
+    pub fn current(&self) -> &View {
+        &self.current_window().view
+    }
+    pub fn current_mut(&mut self) -> &mut View {
+        let current_index = self.current_index;
+        // clippy::indexing_slicing: current_index always points to one of the windows, of which there is at least one
+        #[allow(clippy::indexing_slicing)]
+        &mut self.windows_mut().swap_remove(current_index).view
+    }
+
+    /// Splits the current window in two, both showing its buffer. The first one stays current.
+    pub fn split(&mut self, direction: SplitDirection) {
+        self.root.split(self.current_index, direction);
+        self.place_windows();
+    }
+    /// Closes the current window and moves to the next one. The last window can't be closed.
+    pub fn close_current(&mut self) -> bool {
+        if self.root.window_count() <= 1 {
+            return false;
+        }
+        self.root.close(self.current_index);
+        self.current_index = self
+            .current_index
+            .min(self.root.window_count().saturating_sub(1));
+        self.place_windows();
+        self.current_mut().focus();
+        true
+    }
+    pub fn focus_next(&mut self) {
+        let next = self.current_index.saturating_add(1);
+        self.current_index = if next < self.root.window_count() {
+            next
+        } else {
+            0
+        };
+        self.current_mut().focus();
+    }
+
+    pub fn refresh_status(&mut self) {
+        for window in self.windows_mut() {
+            window.refresh_status();
+        }
+    }
+    pub fn caret_position(&self, origin: Position) -> Position {
+        self.current_window().caret_position(origin)
+    }
+    fn place_windows(&mut self) {
+        let mut separators = Vec::new();
+        self.root
+            .place(Position::default(), self.size, &mut separators);
+        self.separators = separators;
+        self.set_needs_redraw(true);
+    }
+}
+
+impl UIComponent for Layout {
+    fn set_needs_redraw(&mut self, value: bool) {
+        self.needs_redraw = value;
+        if value {
+            for window in self.windows_mut() {
+                window.set_needs_redraw(true);
+            }
+        }
+    }
+    fn needs_redraw(&self) -> bool {
+        self.needs_redraw || self.windows().iter().any(|window| window.needs_redraw())
+    }
+    fn set_size(&mut self, size: Size) {
+        self.size = size;
+        self.place_windows();
+    }
+    fn draw(&mut self, origin: Position) -> Result<(), Error> {
+        for window in self.windows_mut() {
+            window.render(origin);
+        }
+        if self.needs_redraw {
+            for (top, height) in &self.separators {
+                for row in 0..*height {
+                    Terminal::move_caret_to(Position {
+                        row: origin.row.saturating_add(top.row).saturating_add(row),
+                        col: origin.col.saturating_add(top.col),
+                    })?;
+                    Terminal::print("│")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -2,17 +2,25 @@ use std::{fmt, ops::Range};
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 
+use super::{AnnotatedString, Annotation, SearchQuery, TabStops};
+
 #[derive(Copy, Clone)]
 enum GraphemeWidth {
     Half,
     Full,
+    // A tab extends to the next tab stop, so its width depends on the column it starts at.
+    Tab,
 }
 
 impl GraphemeWidth {
-    const fn saturating_add(self, other: usize) -> usize {
+    const fn columns(self, at_col: usize, tab_width: usize) -> usize {
         match self {
-            Self::Half => other.saturating_add(1),
-            Self::Full => other.saturating_add(2),
+            Self::Half => 1,
+            Self::Full => 2,
+            Self::Tab => match at_col.checked_rem(tab_width) {
+                Some(offset) => tab_width.saturating_sub(offset),
+                None => 1,
+            },
         }
     }
 }
@@ -38,6 +46,13 @@ impl Line {
         line_str
             .graphemes(true)
             .map(|grapheme| {
+                if grapheme == "\t" {
+                    return TextFragment {
+                        grapheme: grapheme.to_string(),
+                        rendered_width: GraphemeWidth::Tab,
+                        replacement: None,
+                    };
+                }
                 let (replacement, rendered_width) = Self::replacement_character(grapheme)
                     .map_or_else(
                         || {
@@ -64,7 +79,6 @@ impl Line {
         let width = for_str.width();
         match for_str {
             " " => None,
-            "\t" => Some(' '),
             _ if width > 0 && for_str.trim().is_empty() => Some('␣'),
             _ if width == 0 => {
                 let mut chars = for_str.chars();
@@ -79,47 +93,156 @@ impl Line {
         }
     }
 
-    pub fn get_visible_graphemes(&self, range: Range<usize>) -> String {
+    pub fn get_visible_graphemes(&self, range: Range<usize>, tab_stops: TabStops) -> String {
+        self.get_annotated_visible_graphemes(range, &[], tab_stops)
+            .to_string()
+    }
+
+    /// Returns the graphemes visible within the given column range, each part tagged with the
+    /// annotation covering it. Later annotations take precedence over earlier ones.
+    pub fn get_annotated_visible_graphemes(
+        &self,
+        range: Range<usize>,
+        annotations: &[Annotation],
+        tab_stops: TabStops,
+    ) -> AnnotatedString {
+        let mut result = AnnotatedString::default();
         if range.start >= range.end {
-            return String::new();
+            return result;
         }
-        let mut result = String::new();
-        let mut current_pos = 0;
-        for fragment in &self.fragments {
-            let fragment_end = fragment.rendered_width.saturating_add(current_pos);
+        for (grapheme_index, (fragment, (current_pos, columns))) in self
+            .fragments
+            .iter()
+            .zip(self.layout(tab_stops))
+            .enumerate()
+        {
+            let fragment_end = current_pos.saturating_add(columns);
             if current_pos >= range.end {
                 break;
             }
             if fragment_end > range.start {
-                if fragment_end > range.end || current_pos < range.start {
+                let annotation_type = annotations
+                    .iter()
+                    .rev()
+                    .find(|annotation| annotation.range.contains(&grapheme_index))
+                    .map(|annotation| annotation.annotation_type);
+                if matches!(fragment.rendered_width, GraphemeWidth::Tab) {
+                    // Tabs are blank, so a clipped tab simply shows its visible columns.
+                    let visible_columns = fragment_end
+                        .min(range.end)
+                        .saturating_sub(current_pos.max(range.start));
+                    if tab_stops.visible && current_pos >= range.start {
+                        result.push("→", annotation_type);
+                        result.push(
+                            &" ".repeat(visible_columns.saturating_sub(1)),
+                            annotation_type,
+                        );
+                    } else {
+                        result.push(&" ".repeat(visible_columns), annotation_type);
+                    }
+                } else if fragment_end > range.end || current_pos < range.start {
                     // Clip on the right or left
-                    result.push('⋯');
+                    result.push("⋯", annotation_type);
                 } else if let Some(char) = fragment.replacement {
-                    result.push(char);
+                    result.push(&char.to_string(), annotation_type);
                 } else {
-                    result.push_str(&fragment.grapheme);
+                    result.push(&fragment.grapheme, annotation_type);
                 }
             }
-            current_pos = fragment_end;
         }
         result
     }
 
-    pub fn grapheme_count(&self) -> usize {
-        self.fragments.len()
+    // Returns the column each grapheme starts at, together with the number of columns it takes up.
+    fn layout(&self, tab_stops: TabStops) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.fragments
+            .iter()
+            .scan(0, move |col: &mut usize, fragment| {
+                let start = *col;
+                let columns = fragment.rendered_width.columns(start, tab_stops.width);
+                *col = start.saturating_add(columns);
+                Some((start, columns))
+            })
     }
-    pub fn width_until(&self, grapheme_index: usize) -> usize {
+
+    pub fn graphemes(&self) -> Vec<&str> {
         self.fragments
             .iter()
+            .map(|fragment| fragment.grapheme.as_str())
+            .collect()
+    }
+    pub fn grapheme_count(&self) -> usize {
+        self.fragments.len()
+    }
+    pub fn width_until(&self, grapheme_index: usize, tab_stops: TabStops) -> usize {
+        self.layout(tab_stops)
             .take(grapheme_index)
-            .map(|fragment| match fragment.rendered_width {
-                GraphemeWidth::Half => 1,
-                GraphemeWidth::Full => 2,
-            })
-            .sum()
+            .last()
+            .map_or(0, |(start, columns)| start.saturating_add(columns))
+    }
+    /// Returns the rendered width of the graphemes within `range`.
+    pub fn width_of(&self, range: Range<usize>, tab_stops: TabStops) -> usize {
+        self.width_until(range.end, tab_stops)
+            .saturating_sub(self.width_until(range.start, tab_stops))
+    }
+    /// Returns the index of the grapheme within `range` that covers column `col`, counted from
+    /// the start of the range, or `range.end` if the graphemes in `range` end before `col`.
+    pub fn grapheme_index_at_col(
+        &self,
+        range: Range<usize>,
+        col: usize,
+        tab_stops: TabStops,
+    ) -> usize {
+        let target_col = self.width_until(range.start, tab_stops).saturating_add(col);
+        self.layout(tab_stops)
+            .enumerate()
+            .take(range.end)
+            .skip(range.start)
+            .find(|(_, (start, columns))| start.saturating_add(*columns) > target_col)
+            .map_or(range.end, |(grapheme_index, _)| grapheme_index)
+    }
+
+    /// Breaks the line into display rows no wider than `width` and returns the grapheme range of each row.
+    /// Rows are broken after whitespace where possible and never within a grapheme, so a grapheme
+    /// wider than `width` gets a row of its own.
+    /// Since the caret needs a cell behind the last grapheme, a full last row is followed by an empty one.
+    // clippy::single_range_in_vec_init: A single row spanning all graphemes is what we want here.
+    #[allow(clippy::single_range_in_vec_init)]
+    pub fn wrap(&self, width: usize, tab_stops: TabStops) -> Vec<Range<usize>> {
+        let grapheme_count = self.grapheme_count();
+        if width == 0 {
+            return vec![0..grapheme_count];
+        }
+        let mut rows = Vec::new();
+        let mut row_start = 0;
+        let mut row_width: usize = 0;
+        // The index behind the most recent whitespace in the current row, where it may be broken.
+        let mut break_at = None;
+        for (grapheme_index, (fragment, (_, fragment_width))) in self
+            .fragments
+            .iter()
+            .zip(self.layout(tab_stops))
+            .enumerate()
+        {
+            while row_width.saturating_add(fragment_width) > width && grapheme_index > row_start {
+                let row_end = break_at.take().unwrap_or(grapheme_index);
+                rows.push(row_start..row_end);
+                row_start = row_end;
+                row_width = self.width_of(row_start..grapheme_index, tab_stops);
+            }
+            row_width = row_width.saturating_add(fragment_width);
+            if fragment.grapheme.chars().all(char::is_whitespace) {
+                break_at = Some(grapheme_index.saturating_add(1));
+            }
+        }
+        rows.push(row_start..grapheme_count);
+        if row_width >= width {
+            rows.push(grapheme_count..grapheme_count);
+        }
+        rows
     }
-    pub fn width(&self) -> usize {
-        self.width_until(self.grapheme_count())
+    pub fn width(&self, tab_stops: TabStops) -> usize {
+        self.width_until(self.grapheme_count(), tab_stops)
     }
 
     pub fn insert_char(&mut self, character: char, at: usize) {
@@ -139,6 +262,11 @@ impl Line {
     pub fn append_char(&mut self, character: char) {
         self.insert_char(character, self.grapheme_count());
     }
+    pub fn append_str(&mut self, string: &str) {
+        let mut concat = self.to_string();
+        concat.push_str(string);
+        self.fragments = Self::str_to_fragments(&concat);
+    }
     pub fn delete(&mut self, at: usize) {
         let mut result = String::new();
 
@@ -150,14 +278,128 @@ impl Line {
         self.fragments = Self::str_to_fragments(&result);
     }
 
+    /// Replaces `count` graphemes starting at `at` with `replacement`.
+    pub fn replace(&mut self, at: usize, count: usize, replacement: &str) {
+        let mut result = String::new();
+
+        for (index, fragment) in self.fragments.iter().enumerate() {
+            if index == at {
+                result.push_str(replacement);
+            }
+            if index < at || index >= at.saturating_add(count) {
+                result.push_str(&fragment.grapheme);
+            }
+        }
+        if at >= self.fragments.len() {
+            result.push_str(replacement);
+        }
+        self.fragments = Self::str_to_fragments(&result);
+    }
+
+    /// Returns the text of the graphemes within `range`.
+    pub fn substr(&self, range: Range<usize>) -> String {
+        self.fragments
+            .get(range.start.min(self.fragments.len())..range.end.min(self.fragments.len()))
+            .map_or_else(String::new, |fragments| {
+                fragments
+                    .iter()
+                    .map(|fragment| fragment.grapheme.as_str())
+                    .collect()
+            })
+    }
+
     pub fn delete_last(&mut self) {
         self.delete(self.grapheme_count().saturating_sub(1));
     }
 
     pub fn append(&mut self, other: &Self) {
-        let mut concat = self.to_string();
-        concat.push_str(&other.to_string());
-        self.fragments = Self::str_to_fragments(&concat);
+        self.append_str(&other.to_string());
+    }
+
+    /// Returns the grapheme index of the first match of `query` at or after `from_grapheme_index`.
+    pub fn search_forward(&self, query: &SearchQuery, from_grapheme_index: usize) -> Option<usize> {
+        self.find_all(query)
+            .into_iter()
+            .find(|&grapheme_index| grapheme_index >= from_grapheme_index)
+    }
+    /// Returns the grapheme index of the last match of `query` starting before `before_grapheme_index`.
+    pub fn search_backward(
+        &self,
+        query: &SearchQuery,
+        before_grapheme_index: usize,
+    ) -> Option<usize> {
+        self.find_all(query)
+            .into_iter()
+            .rev()
+            .find(|&grapheme_index| grapheme_index < before_grapheme_index)
+    }
+    // Matches are found on the line's string and then mapped back to grapheme indices.
+    // Every grapheme boundary is tried as a start, so overlapping matches are all found.
+    // Matches which don't end on a grapheme boundary (e.g. the base character of an emoji
+    // sequence or a letter without its combining accent) are dropped.
+    fn find_all(&self, query: &SearchQuery) -> Vec<usize> {
+        let text = self.to_string();
+        let boundaries = self.grapheme_byte_boundaries();
+        let mut matches = Vec::new();
+        let mut grapheme_index = 0;
+        // The last boundary is the end of the line, where no non-empty match can start.
+        let starts = boundaries.get(..self.fragments.len()).unwrap_or_default();
+        while let Some(&start) = starts.get(grapheme_index) {
+            let Some(range) = query.find_at(&text, start) else {
+                break;
+            };
+            if range.start > start {
+                // No match starts in between, so skip ahead to the first grapheme that may start one.
+                grapheme_index = boundaries.partition_point(|&boundary| boundary < range.start);
+                continue;
+            }
+            if !range.is_empty() && boundaries.binary_search(&range.end).is_ok() {
+                matches.push(grapheme_index);
+            }
+            grapheme_index = grapheme_index.saturating_add(1);
+        }
+        matches
+    }
+    /// Returns the number of graphemes the match of `query` starting exactly at `at` spans,
+    /// or `None` if no match starts there.
+    pub fn match_len(&self, at: usize, query: &SearchQuery) -> Option<usize> {
+        let boundaries = self.grapheme_byte_boundaries();
+        let start = *boundaries.get(at)?;
+        let range = query
+            .find_at(&self.to_string(), start)
+            .filter(|range| range.start == start && !range.is_empty())?;
+        let end = boundaries.binary_search(&range.end).ok()?;
+        Some(end.saturating_sub(at))
+    }
+    /// Replaces the match of `query` starting exactly at `at` and returns the number of graphemes
+    /// the replacement consists of, or `None` if no match starts there.
+    pub fn replace_match(
+        &mut self,
+        at: usize,
+        query: &SearchQuery,
+        replacement: &str,
+    ) -> Option<usize> {
+        let text = self.to_string();
+        let boundaries = self.grapheme_byte_boundaries();
+        let start = *boundaries.get(at)?;
+        let (range, expanded) = query.replacement_at(&text, start, replacement)?;
+        let count = boundaries
+            .binary_search(&range.end)
+            .ok()?
+            .saturating_sub(at);
+        self.replace(at, count, &expanded);
+        Some(expanded.graphemes(true).count())
+    }
+    // Returns the byte index at which each grapheme starts, followed by the length of the line in bytes.
+    fn grapheme_byte_boundaries(&self) -> Vec<usize> {
+        let mut boundaries = Vec::with_capacity(self.fragments.len().saturating_add(1));
+        let mut byte_index: usize = 0;
+        for fragment in &self.fragments {
+            boundaries.push(byte_index);
+            byte_index = byte_index.saturating_add(fragment.grapheme.len());
+        }
+        boundaries.push(byte_index);
+        boundaries
     }
 
     pub fn split(&mut self, at: usize) -> Self {
@@ -181,3 +423,113 @@ impl fmt::Display for Line {
         write!(formatter, "{result}")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Line, Range, TabStops};
+    use crate::editor::searchquery::{SearchMode, SearchQuery};
+
+    fn matches(line: &str, query: &str, mode: SearchMode) -> Vec<usize> {
+        Line::from(line).find_all(&SearchQuery::new(query, mode).unwrap())
+    }
+
+    #[test]
+    fn finds_overlapping_matches() {
+        assert_eq!(matches("aaaa", "aa", SearchMode::Literal), vec![0, 1, 2]);
+        assert_eq!(matches("abab ab", "ab", SearchMode::Literal), vec![0, 2, 5]);
+        assert_eq!(matches("aaa", "a+", SearchMode::Regex), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn maps_byte_offsets_to_grapheme_indices() {
+        assert_eq!(matches("äöü ö", "ö", SearchMode::Literal), vec![1, 4]);
+        assert_eq!(matches("日本語の本", "本", SearchMode::Literal), vec![1, 4]);
+        assert_eq!(matches("👍🏽x", "x", SearchMode::Literal), vec![1]);
+    }
+
+    #[test]
+    fn drops_matches_inside_graphemes() {
+        // "e" followed by a combining accent is a single grapheme.
+        assert_eq!(matches("e\u{301}e", "e", SearchMode::Literal), vec![1]);
+        // The thumbs up without its skin tone is only half of the grapheme.
+        assert_eq!(matches("👍🏽👍", "👍", SearchMode::Literal), vec![1]);
+    }
+
+    #[test]
+    fn a_match_cut_off_mid_grapheme_doesnt_hide_an_overlapping_one() {
+        // The longer alternative ends inside "e\u{301}", but the match one grapheme later is valid.
+        assert_eq!(
+            matches("xxe\u{301}", "xxe|xe\u{301}", SearchMode::Regex),
+            vec![1]
+        );
+    }
+
+    #[test]
+    fn ignores_empty_matches() {
+        assert!(matches("abc", "x*", SearchMode::Regex).is_empty());
+        assert_eq!(matches("abc", "b*", SearchMode::Regex), vec![1]);
+    }
+
+    #[test]
+    fn case_insensitive_search_finds_all_cases() {
+        assert_eq!(
+            matches("Foo foo FOO", "foo", SearchMode::CaseInsensitive),
+            vec![0, 4, 8]
+        );
+    }
+
+    #[test]
+    fn replace_match_expands_capture_groups_and_counts_graphemes() {
+        let query = SearchQuery::new(r"(\w+)=(\w+)", SearchMode::Regex).unwrap();
+        let mut line = Line::from("let a=b;");
+        assert_eq!(line.replace_match(4, &query, "$2=$1"), Some(3));
+        assert_eq!(line.to_string(), "let b=a;");
+        assert_eq!(line.replace_match(0, &query, "$2=$1"), None);
+    }
+
+    #[test]
+    fn replace_match_keeps_graphemes_around_the_match() {
+        let query = SearchQuery::new("ö", SearchMode::Literal).unwrap();
+        let mut line = Line::from("äöü");
+        assert_eq!(line.replace_match(1, &query, "日本"), Some(2));
+        assert_eq!(line.to_string(), "ä日本ü");
+    }
+
+    fn wrap(line: &str, width: usize) -> Vec<Range<usize>> {
+        Line::from(line).wrap(width, TabStops::default())
+    }
+
+    #[test]
+    fn wrap_breaks_after_whitespace() {
+        assert_eq!(wrap("ab cd", 4), vec![0..3, 3..5]);
+        assert_eq!(wrap("abcdef", 4), vec![0..4, 4..6]);
+        assert_eq!(wrap("", 4), vec![0..0]);
+    }
+
+    #[test]
+    fn wrap_adds_an_empty_row_behind_a_full_last_row() {
+        assert_eq!(wrap("abcd", 4), vec![0..4, 4..4]);
+    }
+
+    #[test]
+    fn wrap_moves_wide_graphemes_at_the_edge_to_the_next_row() {
+        // "日" would take the 4th and 5th column, so it starts the second row.
+        assert_eq!(wrap("abc日本", 4), vec![0..3, 3..5, 5..5]);
+        // A grapheme wider than the view gets a row of its own.
+        assert_eq!(wrap("日x", 1), vec![0..1, 1..2, 2..2]);
+    }
+
+    #[test]
+    fn wrap_moves_tabs_spanning_the_edge_to_the_next_row() {
+        // The tab spans columns 2 and 3, so it doesn't fit into a row of 3 columns.
+        assert_eq!(wrap("ab\tx", 3), vec![0..2, 2..4, 4..4]);
+        // A tab that ends right at the edge stays and allows breaking behind it.
+        assert_eq!(wrap("abc\tx", 4), vec![0..4, 4..5]);
+    }
+
+    #[test]
+    fn wrap_keeps_the_line_whole_in_a_zero_width_view() {
+        assert_eq!(wrap("abc", 0), vec![0..3]);
+        assert_eq!(wrap("", 0), vec![0..0]);
+    }
+}
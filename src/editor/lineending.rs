@@ -0,0 +1,65 @@
+use std::fmt::{self, Display};
+
+#[derive(Copy, Clone, Default, Eq, PartialEq, Debug)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    /// Returns CRLF if every line in `contents` ends in it, and LF otherwise.
+    /// Files with mixed line endings count as LF, so the carriage returns of their
+    /// CRLF lines stay part of the text and are written back as they were.
+    pub fn detect(contents: &str) -> Self {
+        let crlf_count = contents.matches("\r\n").count();
+        let lf_count = contents.matches('\n').count().saturating_sub(crlf_count);
+        if crlf_count > 0 && lf_count == 0 {
+            Self::CrLf
+        } else {
+            Self::Lf
+        }
+    }
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+        }
+    }
+    pub const fn other(self) -> Self {
+        match self {
+            Self::Lf => Self::CrLf,
+            Self::CrLf => Self::Lf,
+        }
+    }
+}
+
+impl Display for LineEnding {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lf => write!(formatter, "LF"),
+            Self::CrLf => write!(formatter, "CRLF"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LineEnding;
+
+    #[test]
+    fn detects_crlf_only_when_every_line_uses_it() {
+        assert_eq!(LineEnding::detect("a\r\nb\r\n"), LineEnding::CrLf);
+        assert_eq!(LineEnding::detect("a\r\nb"), LineEnding::CrLf);
+        assert_eq!(LineEnding::detect("a\nb\n"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("a\r\nb\nc\r\n"), LineEnding::Lf);
+    }
+
+    #[test]
+    fn defaults_to_lf_without_line_breaks() {
+        assert_eq!(LineEnding::detect(""), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("a"), LineEnding::Lf);
+        // A lone carriage return is not a line break.
+        assert_eq!(LineEnding::detect("a\rb"), LineEnding::Lf);
+    }
+}
@@ -0,0 +1,33 @@
+/// How the line number gutter to the left of the text is rendered.
+#[derive(Copy, Clone, Default, Eq, PartialEq)]
+pub enum LineNumbers {
+    #[default]
+    Off,
+    // Every line shows its own number.
+    Absolute,
+    // Every line shows its distance to the caret line, which shows 0.
+    Relative,
+    // Like `Relative`, but the caret line shows its own number.
+    Hybrid,
+}
+
+impl LineNumbers {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(Self::Off),
+            "absolute" => Some(Self::Absolute),
+            "relative" => Some(Self::Relative),
+            "hybrid" => Some(Self::Hybrid),
+            _ => None,
+        }
+    }
+
+    /// Returns the number shown next to the line at `line_index`, given the line the caret is on.
+    pub const fn label(self, line_index: usize, caret_line_index: usize) -> usize {
+        match self {
+            Self::Relative => line_index.abs_diff(caret_line_index),
+            Self::Hybrid if line_index != caret_line_index => line_index.abs_diff(caret_line_index),
+            Self::Off | Self::Absolute | Self::Hybrid => line_index.saturating_add(1),
+        }
+    }
+}
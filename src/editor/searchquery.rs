@@ -0,0 +1,137 @@
+use regex::{escape, Error, Regex};
+use std::ops::Range;
+
+#[derive(Copy, Clone, Default, Eq, PartialEq)]
+pub enum SearchMode {
+    #[default]
+    Literal,
+    CaseInsensitive,
+    Regex,
+}
+
+impl SearchMode {
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Literal => Self::CaseInsensitive,
+            Self::CaseInsensitive => Self::Regex,
+            Self::Regex => Self::Literal,
+        }
+    }
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Literal => "literal",
+            Self::CaseInsensitive => "ignore case",
+            Self::Regex => "regex",
+        }
+    }
+}
+
+/// A compiled search pattern. All modes are backed by a regular expression:
+/// literal queries are escaped, case-insensitive queries are escaped and get the `i` flag.
+pub struct SearchQuery {
+    mode: SearchMode,
+    regex: Regex,
+}
+
+impl SearchQuery {
+    pub fn new(text: &str, mode: SearchMode) -> Result<Self, Error> {
+        let pattern = match mode {
+            SearchMode::Literal => escape(text),
+            SearchMode::CaseInsensitive => format!("(?i){}", escape(text)),
+            SearchMode::Regex => text.to_string(),
+        };
+        Ok(Self {
+            mode,
+            regex: Regex::new(&pattern)?,
+        })
+    }
+
+    /// Returns the byte range of the leftmost match in `haystack` that starts at or after byte
+    /// index `from`. The text before `from` still counts for anchors and word boundaries.
+    pub fn find_at(&self, haystack: &str, from: usize) -> Option<Range<usize>> {
+        self.regex
+            .find_at(haystack, from)
+            .map(|found| found.range())
+    }
+
+    /// Matches the query exactly at byte index `at` of `haystack` and returns the byte range of the
+    /// match together with the text it should be replaced with.
+    /// In regex mode, capture group references such as `$1` or `${name}` in `replacement` are expanded.
+    pub fn replacement_at(
+        &self,
+        haystack: &str,
+        at: usize,
+        replacement: &str,
+    ) -> Option<(Range<usize>, String)> {
+        let captures = self.regex.captures_at(haystack, at)?;
+        let found = captures.get(0)?;
+        if found.start() != at || found.is_empty() {
+            return None;
+        }
+        let expanded = if self.mode == SearchMode::Regex {
+            let mut expanded = String::new();
+            captures.expand(replacement, &mut expanded);
+            expanded
+        } else {
+            replacement.to_string()
+        };
+        Some((found.range(), expanded))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SearchMode, SearchQuery};
+
+    fn query(text: &str, mode: SearchMode) -> SearchQuery {
+        SearchQuery::new(text, mode).unwrap()
+    }
+
+    #[test]
+    fn literal_queries_match_case_and_special_characters_exactly() {
+        let literal = query("a.b", SearchMode::Literal);
+        assert_eq!(literal.find_at("axb a.b", 0), Some(4..7));
+        assert_eq!(literal.find_at("A.B", 0), None);
+    }
+
+    #[test]
+    fn case_insensitive_queries_ignore_case() {
+        let ignore_case = query("straße", SearchMode::CaseInsensitive);
+        assert_eq!(ignore_case.find_at("STRASSE Straße", 0), Some(8..15));
+        assert_eq!(
+            query("a+", SearchMode::CaseInsensitive).find_at("aa A+", 0),
+            Some(3..5)
+        );
+    }
+
+    #[test]
+    fn invalid_regexes_are_rejected() {
+        assert!(SearchQuery::new("(unclosed", SearchMode::Regex).is_err());
+        assert!(SearchQuery::new("(unclosed", SearchMode::Literal).is_ok());
+    }
+
+    #[test]
+    fn find_at_keeps_the_text_before_the_start_as_context() {
+        let word = query(r"\bb", SearchMode::Regex);
+        assert_eq!(word.find_at("ab b", 1), Some(3..4));
+    }
+
+    #[test]
+    fn regex_replacements_expand_capture_groups() {
+        let regex = query(r"(\w+)@(?<host>\w+)", SearchMode::Regex);
+        assert_eq!(
+            regex.replacement_at("to me@home", 3, "$2 at ${host}: $1"),
+            Some((3..10, String::from("home at home: me")))
+        );
+        assert_eq!(regex.replacement_at("to me@home", 2, "$1"), None);
+    }
+
+    #[test]
+    fn literal_replacements_are_taken_as_they_are() {
+        let literal = query("a", SearchMode::Literal);
+        assert_eq!(
+            literal.replacement_at("a", 0, "$1"),
+            Some((0..1, String::from("$1")))
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use super::{Indentation, LineNumbers, TabStops};
+
+/// Editor options, set through `--option` or `--option=value` command line arguments.
+#[derive(Clone, Copy, Default)]
+pub struct Settings {
+    /// Also send copied text to the host terminal's clipboard via OSC 52.
+    pub osc52: bool,
+    /// Show a line number gutter: `--line-numbers[=absolute|relative|hybrid|off]`.
+    pub line_numbers: LineNumbers,
+    /// Break long lines into multiple display rows: `--soft-wrap`.
+    pub soft_wrap: bool,
+    /// Tab stop distance and tab markers: `--tab-width=N` and `--show-tabs`.
+    pub tab_stops: TabStops,
+    /// What the Tab key inserts: `--indent=tabs` or `--indent=N` for N spaces.
+    pub indentation: Indentation,
+    /// Reload the file when another program changes it, unless there are unsaved changes: `--auto-reload`.
+    pub auto_reload: bool,
+}
+
+impl Settings {
+    /// Applies a single option, given without its leading `--`.
+    pub fn apply(&mut self, option: &str) -> Result<(), String> {
+        let (name, value) = match option.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (option, None),
+        };
+        match (name, value) {
+            ("osc52", None) => self.osc52 = true,
+            ("soft-wrap", None) => self.soft_wrap = true,
+            ("show-tabs", None) => self.tab_stops.visible = true,
+            ("auto-reload", None) => self.auto_reload = true,
+            ("indent", Some(value)) => {
+                self.indentation = Indentation::from_name(value)
+                    .ok_or_else(|| format!("Invalid value for --indent: {value}"))?;
+            }
+            ("tab-width", Some(value)) => {
+                self.tab_stops.width = value
+                    .parse()
+                    .ok()
+                    .filter(|&width| width > 0)
+                    .ok_or_else(|| format!("Invalid value for --tab-width: {value}"))?;
+            }
+            ("line-numbers", None) => self.line_numbers = LineNumbers::Absolute,
+            ("line-numbers", Some(value)) => {
+                self.line_numbers = LineNumbers::from_name(value)
+                    .ok_or_else(|| format!("Invalid value for --line-numbers: {value}"))?;
+            }
+            _ => return Err(format!("Unknown option: --{option}")),
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
 use std::io::Error;
 
-use super::{DocumentStatus, Size, Terminal, UIComponent};
+use super::{DocumentStatus, Position, Size, Terminal, UIComponent};
 
 #[derive(Default)]
 pub struct StatusBar {
@@ -30,7 +30,7 @@ impl UIComponent for StatusBar {
     fn set_size(&mut self, size: Size) {
         self.size = size;
     }
-    fn draw(&mut self, origin_row: usize) -> Result<(), Error> {
+    fn draw(&mut self, origin: Position) -> Result<(), Error> {
         //Assemble the first part of the status bar
         let line_count = self.current_status.line_count_to_string();
         let modified_indicator = self.current_status.modified_indicator_to_string();
@@ -40,18 +40,27 @@ impl UIComponent for StatusBar {
             self.current_status.file_name
         );
 
-        // Assemble the whole status bar, with the position indicator at the back
-        let position_indicator = self.current_status.position_indicator_to_string();
+        // Assemble the whole status bar, with the encoding, line ending, indentation, file type and position indicator at the back
+        let position_indicator = format!(
+            "{} | {} | {} | {} | {}",
+            self.current_status.encoding_to_string(),
+            self.current_status.line_ending_to_string(),
+            self.current_status.indentation_to_string(),
+            self.current_status.file_type_to_string(),
+            self.current_status.position_indicator_to_string()
+        );
         let remainder_len = self.size.width.saturating_sub(beginning.len());
         let status = format!("{beginning}{position_indicator:>remainder_len$}");
 
-        //Only print out the status if it fits. Otherwise write out an empty string to ensure the row is cleared.
+        // If the status doesn't fit, as in a narrow window, keep only the position at the back.
+        // If even that doesn't fit, the beginning gets cut off at the edge of the bar.
         let to_print = if status.len() <= self.size.width {
             status
         } else {
-            String::new()
+            let position_indicator = self.current_status.position_indicator_to_string();
+            format!("{beginning}{position_indicator:>remainder_len$}")
         };
-        Terminal::print_inverted_row(origin_row, &to_print)?;
+        Terminal::print_inverted_row(origin, self.size.width, &to_print)?;
 
         Ok(())
     }
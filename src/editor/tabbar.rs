@@ -0,0 +1,127 @@
+use std::{io::Error, ops::Range};
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use super::{AnnotatedString, AnnotationType, Position, Size, Terminal, UIComponent};
+
+#[derive(Clone, Default, Eq, PartialEq)]
+pub struct Tab {
+    pub name: String,
+    pub is_modified: bool,
+}
+
+impl Tab {
+    fn label(&self, number: usize) -> String {
+        let modified_indicator = if self.is_modified { "*" } else { "" };
+        format!(" {number} {}{modified_indicator} ", self.name)
+    }
+}
+
+/// Shows a tab for each open buffer, highlighting the one in the current window.
+#[derive(Default)]
+pub struct TabBar {
+    tabs: Vec<Tab>,
+    current_index: usize,
+    needs_redraw: bool,
+    size: Size,
+}
+
+impl TabBar {
+    pub fn update_tabs(&mut self, tabs: Vec<Tab>, current_index: usize) {
+        if tabs != self.tabs || current_index != self.current_index {
+            self.tabs = tabs;
+            self.current_index = current_index;
+            self.set_needs_redraw(true);
+        }
+    }
+    // Returns the tabs to show: all of them if they fit, otherwise as many as fit around the
+    // current one, leaving room for a marker on each side.
+    fn visible_range(&self, widths: &[usize]) -> Range<usize> {
+        if widths.iter().sum::<usize>() <= self.size.width {
+            return 0..widths.len();
+        }
+        let available = self.size.width.saturating_sub(2);
+        let width_of = |index: usize| widths.get(index).copied().unwrap_or_default();
+        let mut start = self.current_index;
+        let mut end = self.current_index.saturating_add(1);
+        let mut used = width_of(self.current_index);
+        loop {
+            let mut has_grown = false;
+            if end < widths.len() && used.saturating_add(width_of(end)) <= available {
+                used = used.saturating_add(width_of(end));
+                end = end.saturating_add(1);
+                has_grown = true;
+            }
+            if let Some(before) = start.checked_sub(1) {
+                if used.saturating_add(width_of(before)) <= available {
+                    used = used.saturating_add(width_of(before));
+                    start = before;
+                    has_grown = true;
+                }
+            }
+            if !has_grown {
+                return start..end;
+            }
+        }
+    }
+}
+
+// Cuts `text` off after as many graphemes as fit into `width` columns.
+fn truncate(text: &str, width: usize) -> String {
+    let mut used: usize = 0;
+    text.graphemes(true)
+        .take_while(|grapheme| {
+            used = used.saturating_add(grapheme.width());
+            used <= width
+        })
+        .collect()
+}
+
+impl UIComponent for TabBar {
+    fn set_needs_redraw(&mut self, value: bool) {
+        self.needs_redraw = value;
+    }
+    fn needs_redraw(&self) -> bool {
+        self.needs_redraw
+    }
+    fn set_size(&mut self, size: Size) {
+        self.size = size;
+    }
+    fn draw(&mut self, origin: Position) -> Result<(), Error> {
+        let labels: Vec<String> = self
+            .tabs
+            .iter()
+            .enumerate()
+            .map(|(index, tab)| tab.label(index.saturating_add(1)))
+            .collect();
+        let widths: Vec<usize> = labels.iter().map(|label| label.width()).collect();
+        let visible = self.visible_range(&widths);
+        let is_truncated = visible.len() < labels.len();
+        let label_width = if is_truncated {
+            self.size.width.saturating_sub(2)
+        } else {
+            self.size.width
+        };
+
+        let mut tab_line = AnnotatedString::default();
+        if is_truncated {
+            tab_line.push(if visible.start > 0 { "<" } else { " " }, None);
+        }
+        for (index, label) in labels
+            .iter()
+            .enumerate()
+            .take(visible.end)
+            .skip(visible.start)
+        {
+            let annotation_type =
+                (index == self.current_index).then_some(AnnotationType::ActiveTab);
+            // Only the current tab can be too wide on its own.
+            tab_line.push(&truncate(label, label_width), annotation_type);
+        }
+        if is_truncated && visible.end < labels.len() {
+            tab_line.push(">", None);
+        }
+        Terminal::print_annotated_row(origin, self.size.width, &tab_line)
+    }
+}
@@ -0,0 +1,17 @@
+/// How tabs are laid out and rendered.
+#[derive(Copy, Clone)]
+pub struct TabStops {
+    /// The distance between two tab stops, in columns.
+    pub width: usize,
+    /// Whether tabs start with a visible `→` marker instead of being blank.
+    pub visible: bool,
+}
+
+impl Default for TabStops {
+    fn default() -> Self {
+        Self {
+            width: 4,
+            visible: false,
+        }
+    }
+}
@@ -1,5 +1,8 @@
 use crossterm::cursor::{Hide, MoveTo, Show};
-use crossterm::style::{Attribute, Print};
+use crossterm::event::{
+    DisableBracketedPaste, DisableFocusChange, EnableBracketedPaste, EnableFocusChange,
+};
+use crossterm::style::{Attribute, Color, Print, ResetColor, SetForegroundColor};
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, size, Clear, ClearType, DisableLineWrap, EnableLineWrap,
     EnterAlternateScreen, LeaveAlternateScreen, SetTitle,
@@ -7,7 +10,7 @@ use crossterm::terminal::{
 use crossterm::{queue, Command};
 use std::io::{stdout, Error, Write};
 
-use super::{Position, Size};
+use super::{AnnotatedString, AnnotationType, Position, Size};
 
 /// Represents the Terminal.
 /// Edge Case for platforms where `usize` < `u16`:
@@ -19,6 +22,8 @@ pub struct Terminal;
 
 impl Terminal {
     pub fn terminate() -> Result<(), Error> {
+        Self::disable_bracketed_paste()?;
+        Self::disable_focus_change()?;
         Self::leave_alternate_screen()?;
         Self::enable_line_wrap()?;
         Self::show_caret()?;
@@ -30,6 +35,8 @@ impl Terminal {
         enable_raw_mode()?;
         Self::enter_alternate_screen()?;
         Self::disable_line_wrap()?;
+        Self::enable_bracketed_paste()?;
+        Self::enable_focus_change()?;
         Self::clear_screen()?;
         Self::execute()?;
         Ok(())
@@ -75,6 +82,57 @@ impl Terminal {
         Self::queue_command(EnableLineWrap)?;
         Ok(())
     }
+    pub fn enable_bracketed_paste() -> Result<(), Error> {
+        Self::queue_command(EnableBracketedPaste)?;
+        Ok(())
+    }
+    pub fn disable_bracketed_paste() -> Result<(), Error> {
+        Self::queue_command(DisableBracketedPaste)?;
+        Ok(())
+    }
+    pub fn enable_focus_change() -> Result<(), Error> {
+        Self::queue_command(EnableFocusChange)?;
+        Ok(())
+    }
+    pub fn disable_focus_change() -> Result<(), Error> {
+        Self::queue_command(DisableFocusChange)?;
+        Ok(())
+    }
+    /// Asks the host terminal to put `text` into the system clipboard by emitting an OSC 52 sequence.
+    /// This works across SSH sessions, as long as the terminal supports it.
+    pub fn copy_to_clipboard(text: &str) -> Result<(), Error> {
+        Self::write_clipboard_sequence(&mut stdout(), text)
+    }
+    /// Writes the OSC 52 sequence for `text` to `writer`.
+    pub fn write_clipboard_sequence<W: Write>(writer: &mut W, text: &str) -> Result<(), Error> {
+        write!(
+            writer,
+            "\x1b]52;c;{}\x07",
+            Self::base64_encode(text.as_bytes())
+        )
+    }
+    // clippy::arithmetic_side_effects: Only shifts and masks within a u32 that holds at most 24 bits.
+    // clippy::indexing_slicing: All indices are masked to 6 bits and therefore within the alphabet.
+    #[allow(clippy::arithmetic_side_effects, clippy::indexing_slicing)]
+    fn base64_encode(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8; 64] =
+            b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut result = String::new();
+        for chunk in bytes.chunks(3) {
+            let triple = chunk.iter().enumerate().fold(0u32, |acc, (index, &byte)| {
+                acc | (u32::from(byte) << (16 - 8 * index))
+            });
+            for index in 0..4 {
+                if index <= chunk.len() {
+                    let sextet = (triple >> (18 - 6 * index)) & 0x3F;
+                    result.push(char::from(ALPHABET[usize::try_from(sextet).unwrap_or(0)]));
+                } else {
+                    result.push('=');
+                }
+            }
+        }
+        result
+    }
     pub fn set_title(title: &str) -> Result<(), Error> {
         Self::queue_command(SetTitle(title))?;
         Ok(())
@@ -89,17 +147,72 @@ impl Terminal {
         Self::print(line_text)?;
         Ok(())
     }
-    pub fn print_inverted_row(row: usize, line_text: &str) -> Result<(), Error> {
-        let width = Self::size()?.width;
-        Self::print_row(
-            row,
-            &format!(
-                "{}{:width$.width$}{}",
-                Attribute::Reverse,
-                line_text,
-                Attribute::Reset
-            ),
-        )
+    /// Prints `line_text` at `origin`, blanking the rest of the `width` columns from there
+    /// but leaving the rest of the row alone.
+    pub fn print_row_part(origin: Position, width: usize, line_text: &str) -> Result<(), Error> {
+        Self::clear_row_part(origin, width)?;
+        Self::print(line_text)?;
+        Ok(())
+    }
+    // Blanks `width` columns starting at `origin` and moves the caret back to `origin`.
+    fn clear_row_part(origin: Position, width: usize) -> Result<(), Error> {
+        Self::move_caret_to(origin)?;
+        Self::print(&" ".repeat(width))?;
+        Self::move_caret_to(origin)?;
+        Ok(())
+    }
+    pub fn print_annotated_row(
+        origin: Position,
+        width: usize,
+        annotated_string: &AnnotatedString,
+    ) -> Result<(), Error> {
+        Self::clear_row_part(origin, width)?;
+        for part in annotated_string.parts() {
+            match part.annotation_type {
+                None => Self::print(&part.string)?,
+                Some(AnnotationType::Selection | AnnotationType::ActiveTab) => Self::print(
+                    &format!("{}{}{}", Attribute::Reverse, part.string, Attribute::Reset),
+                )?,
+                Some(AnnotationType::CurrentLineNumber) => Self::print(&format!(
+                    "{}{}{}",
+                    Attribute::Bold,
+                    part.string,
+                    Attribute::Reset
+                ))?,
+                Some(annotation_type) => {
+                    Self::queue_command(SetForegroundColor(Self::color_for(annotation_type)))?;
+                    Self::print(&part.string)?;
+                    Self::queue_command(ResetColor)?;
+                }
+            }
+        }
+        Ok(())
+    }
+    const fn color_for(annotation_type: AnnotationType) -> Color {
+        match annotation_type {
+            AnnotationType::Keyword => Color::Magenta,
+            AnnotationType::Type => Color::Cyan,
+            AnnotationType::String | AnnotationType::Added => Color::Green,
+            AnnotationType::Number => Color::Yellow,
+            AnnotationType::Match | AnnotationType::Removed => Color::Red,
+            AnnotationType::Comment | AnnotationType::LineNumber => Color::DarkGrey,
+            AnnotationType::Selection
+            | AnnotationType::CurrentLineNumber
+            | AnnotationType::ActiveTab => Color::Reset,
+        }
+    }
+    pub fn print_inverted_row(
+        origin: Position,
+        width: usize,
+        line_text: &str,
+    ) -> Result<(), Error> {
+        Self::move_caret_to(origin)?;
+        Self::print(&format!(
+            "{}{:width$.width$}{}",
+            Attribute::Reverse,
+            line_text,
+            Attribute::Reset
+        ))
     }
     /// Returns the current size of this Terminal.
     /// Edge Case for systems with `usize` < `u16`:
@@ -124,3 +237,40 @@ impl Terminal {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Terminal;
+
+    #[test]
+    fn base64_encode_pads_partial_chunks() {
+        assert_eq!(Terminal::base64_encode(b""), "");
+        assert_eq!(Terminal::base64_encode(b"f"), "Zg==");
+        assert_eq!(Terminal::base64_encode(b"fo"), "Zm8=");
+        assert_eq!(Terminal::base64_encode(b"foo"), "Zm9v");
+        assert_eq!(Terminal::base64_encode(b"foob"), "Zm9vYg==");
+        assert_eq!(Terminal::base64_encode(b"fooba"), "Zm9vYmE=");
+        assert_eq!(Terminal::base64_encode(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn base64_encode_handles_non_ascii_bytes() {
+        assert_eq!(Terminal::base64_encode("é".as_bytes()), "w6k=");
+        assert_eq!(Terminal::base64_encode("日本".as_bytes()), "5pel5pys");
+        assert_eq!(Terminal::base64_encode(&[0xFF, 0xFE, 0xFD]), "//79");
+    }
+
+    #[test]
+    fn write_clipboard_sequence_emits_osc_52() {
+        let mut output = Vec::new();
+        Terminal::write_clipboard_sequence(&mut output, "hi\n").unwrap();
+        assert_eq!(output, b"\x1b]52;c;aGkK\x07");
+    }
+
+    #[test]
+    fn write_clipboard_sequence_handles_empty_text() {
+        let mut output = Vec::new();
+        Terminal::write_clipboard_sequence(&mut output, "").unwrap();
+        assert_eq!(output, b"\x1b]52;c;\x07");
+    }
+}
@@ -1,6 +1,6 @@
 use std::io::Error;
 
-use super::Size;
+use super::{Position, Size};
 
 pub trait UIComponent {
     // Marks this UI component as in need of redrawing (or not)
@@ -17,9 +17,9 @@ pub trait UIComponent {
     fn set_size(&mut self, size: Size);
 
     // Draw this component if it's visible and in need of redrawing
-    fn render(&mut self, origin_row: usize) {
+    fn render(&mut self, origin: Position) {
         if self.needs_redraw() {
-            match self.draw(origin_row) {
+            match self.draw(origin) {
                 Ok(()) => self.set_needs_redraw(false),
                 Err(err) => {
                     #[cfg(debug_assertions)]
@@ -31,5 +31,5 @@ pub trait UIComponent {
         }
     }
     // Method to actually draw the component, must be implemented by each component
-    fn draw(&mut self, origin_row: usize) -> Result<(), Error>;
+    fn draw(&mut self, origin: Position) -> Result<(), Error>;
 }
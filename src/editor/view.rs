@@ -1,127 +1,899 @@
-use std::{cmp::min, io::Error};
+use std::{
+    cell::RefCell,
+    cmp::{min, Ordering},
+    io::Error,
+    iter, mem,
+    ops::Range,
+    rc::{Rc, Weak},
+};
 
 use super::{
     command::{Edit, Move},
-    DocumentStatus, Line, Position, Size, Terminal, UIComponent, NAME, VERSION,
+    AnnotatedString, Annotation, AnnotationType, DocumentStatus, Encoding, FileType, Indentation,
+    Line, LineEnding, LineNumbers, Position, SearchQuery, Size, TabStops, Terminal, UIComponent,
+    NAME, VERSION,
 };
 mod buffer;
-use buffer::Buffer;
+pub use buffer::Buffer;
+mod diff;
+pub use diff::{DiffKind, DiffLine};
 mod fileinfo;
 use fileinfo::FileInfo;
+mod filestamp;
+use filestamp::FileStamp;
+mod highlighter;
+mod history;
+mod replaceinfo;
+use replaceinfo::ReplaceInfo;
+mod searchinfo;
+use searchinfo::SearchInfo;
+mod swapfile;
+use swapfile::SwapFile;
+
+#[derive(Copy, Clone)]
+enum SearchDirection {
+    Forward,
+    Backward,
+}
 
-#[derive(Copy, Clone, Default)]
+#[derive(Copy, Clone, Default, Eq, PartialEq)]
 pub struct Location {
     pub grapheme_index: usize,
     pub line_index: usize,
 }
+
+// Locations are ordered by their position in the text: first by line, then by grapheme.
+impl Ord for Location {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.line_index
+            .cmp(&other.line_index)
+            .then(self.grapheme_index.cmp(&other.grapheme_index))
+    }
+}
+impl PartialOrd for Location {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 #[derive(Default)]
 pub struct View {
-    buffer: Buffer,
+    // Shared with all other views on the same buffer, which see each other's edits.
+    buffer: Rc<RefCell<Buffer>>,
+    // The buffer revision last drawn, to notice edits made through other views.
+    drawn_revision: usize,
+    // The caret and scroll offsets of the buffers this view showed before.
+    remembered_locations: Vec<(Weak<RefCell<Buffer>>, Location, Position, usize)>,
     needs_redraw: bool,
     size: Size,
     text_location: Location,
+    // The first visible line and column. Soft wrap has no horizontal scrolling, so there the
+    // column is ignored.
     scroll_offset: Position,
+    // With soft wrap, the first visible display row of the top line, which spares wrapping all
+    // lines above the view to find out where it starts.
+    top_wrapped_row: usize,
+    selection_anchor: Option<Location>,
+    search_info: Option<SearchInfo>,
+    replace_info: Option<ReplaceInfo>,
+    line_numbers: LineNumbers,
+    soft_wrap: bool,
+    tab_stops: TabStops,
+    // Used unless the buffer's own indentation could be detected.
+    default_indentation: Indentation,
 }
 
 impl View {
     pub fn get_status(&self) -> DocumentStatus {
         DocumentStatus {
-            total_lines: self.buffer.height(),
+            total_lines: self.buffer.borrow().height(),
             current_line_index: self.text_location.line_index,
-            file_name: format!("{}", self.buffer.file_info),
-            is_modified: self.buffer.dirty,
+            file_name: format!("{}", self.buffer.borrow().file_info),
+            file_type: self.buffer.borrow().file_info.get_file_type(),
+            indentation: self.indentation(),
+            encoding: self.buffer.borrow().encoding,
+            line_ending: self.buffer.borrow().line_ending,
+            has_final_newline: !self.buffer.borrow().missing_final_newline,
+            is_modified: self.buffer.borrow().dirty,
         }
     }
 
-    pub const fn is_file_loaded(&self) -> bool {
-        self.buffer.is_file_loaded()
+    pub fn is_file_loaded(&self) -> bool {
+        self.buffer.borrow().is_file_loaded()
+    }
+
+    // region: buffers
+
+    pub fn buffer(&self) -> Rc<RefCell<Buffer>> {
+        Rc::clone(&self.buffer)
+    }
+    /// Shows `buffer` instead of the current one. The caret and scroll offset of the current
+    /// buffer are remembered, and those remembered for `buffer` are restored.
+    pub fn show_buffer(&mut self, buffer: Rc<RefCell<Buffer>>) {
+        if Rc::ptr_eq(&buffer, &self.buffer) {
+            return;
+        }
+        let previous = mem::replace(&mut self.buffer, buffer);
+        self.remembered_locations.retain(|(remembered, _, _, _)| {
+            remembered.strong_count() > 0 && remembered.as_ptr() != Rc::as_ptr(&previous)
+        });
+        self.remembered_locations.push((
+            Rc::downgrade(&previous),
+            self.text_location,
+            self.scroll_offset,
+            self.top_wrapped_row,
+        ));
+        let remembered = self
+            .remembered_locations
+            .iter()
+            .position(|(remembered, _, _, _)| remembered.as_ptr() == Rc::as_ptr(&self.buffer))
+            .map(|index| self.remembered_locations.remove(index));
+        let (location, scroll_offset, top_wrapped_row) = remembered.map_or_else(
+            Default::default,
+            |(_, location, scroll_offset, top_wrapped_row)| {
+                (location, scroll_offset, top_wrapped_row)
+            },
+        );
+        self.text_location = location;
+        self.scroll_offset = scroll_offset;
+        self.top_wrapped_row = top_wrapped_row;
+        self.selection_anchor = None;
+        self.focus();
+        self.set_needs_redraw(true);
+    }
+    /// Creates another view on the same buffer, with the same caret, scroll offset and settings.
+    pub fn duplicate(&self) -> Self {
+        Self {
+            buffer: Rc::clone(&self.buffer),
+            needs_redraw: true,
+            size: self.size,
+            text_location: self.text_location,
+            scroll_offset: self.scroll_offset,
+            top_wrapped_row: self.top_wrapped_row,
+            line_numbers: self.line_numbers,
+            soft_wrap: self.soft_wrap,
+            tab_stops: self.tab_stops,
+            default_indentation: self.default_indentation,
+            ..Self::default()
+        }
     }
+    /// Prepares the view to take input again. Other views may have changed the buffer in the
+    /// meantime, so the caret is moved back into the text.
+    pub fn focus(&mut self) {
+        self.jump_to(self.text_location);
+    }
+
+    // endregion
 
     // region: file i/o
-    pub fn load(&mut self, file_name: &str) -> Result<(), Error> {
-        let buffer = Buffer::load(file_name)?;
-        self.buffer = buffer;
+
+    pub fn discard_swap_file(&mut self) {
+        self.buffer.borrow_mut().discard_swap_file();
+    }
+    pub fn recover_swap_file(&mut self) -> Result<(), Error> {
+        self.buffer.borrow_mut().recover_swap_file()?;
+        self.text_location = Location::default();
+        self.scroll_offset = Position::default();
+        self.top_wrapped_row = 0;
+        self.set_needs_redraw(true);
+        Ok(())
+    }
+
+    /// Reads the file again, keeping the caret where it was as far as possible.
+    pub fn reload(&mut self) -> Result<(), Error> {
+        self.buffer.borrow_mut().reload()?;
+        self.selection_anchor = None;
+        self.jump_to(self.text_location);
+        Ok(())
+    }
+    pub fn is_changed_on_disk(&self) -> bool {
+        self.buffer.borrow().is_changed_on_disk()
+    }
+    /// Returns the changes between the text and the file on disk, or nothing if there are none.
+    pub fn diff_with_disk(&self) -> Result<Vec<DiffLine>, Error> {
+        self.buffer.borrow().diff_with_disk()
+    }
+
+    /// Reads the file again as `encoding`, moving the caret back to the start.
+    pub fn reload_as(&mut self, encoding: Encoding) -> Result<(), Error> {
+        self.buffer.borrow_mut().reload_as(encoding)?;
+        self.text_location = Location::default();
+        self.scroll_offset = Position::default();
+        self.top_wrapped_row = 0;
         self.set_needs_redraw(true);
         Ok(())
     }
 
+    pub fn fits_encoding(&self) -> bool {
+        self.buffer.borrow().fits_encoding()
+    }
+    /// Switches the encoding the buffer is saved in.
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.buffer.borrow_mut().set_encoding(encoding);
+    }
     pub fn save(&mut self) -> Result<(), Error> {
-        self.buffer.save()
+        self.buffer.borrow_mut().save()
     }
     pub fn save_as(&mut self, file_name: &str) -> Result<(), Error> {
-        self.buffer.save_as(file_name)
+        self.buffer.borrow_mut().save_as(file_name)
+    }
+    /// Switches the buffer between LF and CRLF line endings and returns the new style.
+    /// This takes effect on the next save.
+    pub fn convert_line_endings(&mut self) -> LineEnding {
+        let line_ending = self.buffer.borrow().line_ending.other();
+        self.buffer.borrow_mut().set_line_ending(line_ending);
+        // Removed carriage returns may leave the caret behind the end of its line.
+        self.snap_to_valid_grapheme();
+        self.set_needs_redraw(true);
+        line_ending
+    }
+
+    // endregion
+
+    // region: search
+
+    pub fn enter_search(&mut self) {
+        self.buffer.borrow_mut().seal_history();
+        self.clear_selection();
+        self.search_info = Some(SearchInfo {
+            prev_location: self.text_location,
+            prev_scroll_offset: self.scroll_offset,
+            prev_top_wrapped_row: self.top_wrapped_row,
+            query: None,
+        });
+    }
+    pub fn exit_search(&mut self) {
+        self.search_info = None;
+    }
+    pub fn dismiss_search(&mut self) {
+        if let Some(search_info) = &self.search_info {
+            self.text_location = search_info.prev_location;
+            self.scroll_offset = search_info.prev_scroll_offset;
+            self.top_wrapped_row = search_info.prev_top_wrapped_row;
+            // ensure the previous location is still visible even if the terminal has been resized during search.
+            self.scroll_text_location_into_view();
+            self.set_needs_redraw(true);
+        }
+        self.search_info = None;
+    }
+    pub fn search(&mut self, query: SearchQuery) {
+        if let Some(search_info) = &mut self.search_info {
+            search_info.query = Some(query);
+        }
+        self.search_in_direction(self.text_location, SearchDirection::Forward);
+    }
+    pub fn search_next(&mut self) {
+        let from = Location {
+            line_index: self.text_location.line_index,
+            grapheme_index: self.text_location.grapheme_index.saturating_add(1),
+        };
+        self.search_in_direction(from, SearchDirection::Forward);
+    }
+    pub fn search_prev(&mut self) {
+        self.search_in_direction(self.text_location, SearchDirection::Backward);
+    }
+    fn search_in_direction(&mut self, from: Location, direction: SearchDirection) {
+        let Some(query) = self
+            .search_info
+            .as_ref()
+            .and_then(|info| info.query.as_ref())
+        else {
+            return;
+        };
+        let found = match direction {
+            SearchDirection::Forward => self.buffer.borrow().search_forward(query, from),
+            SearchDirection::Backward => self.buffer.borrow().search_backward(query, from),
+        };
+        if let Some(location) = found {
+            self.text_location = location;
+            self.scroll_text_location_into_view();
+        }
+    }
+
+    // endregion
+
+    // region: replace
+
+    /// Starts replacing `query` with `replacement`, walking all matches from the caret to the end
+    /// of the buffer and then from the top back to the caret.
+    /// Returns `false` if there is no match at all.
+    pub fn enter_replace(&mut self, query: SearchQuery, replacement: &str) -> bool {
+        self.buffer.borrow_mut().seal_history();
+        self.clear_selection();
+        self.replace_info = Some(ReplaceInfo {
+            query,
+            replacement: replacement.to_string(),
+            prev_location: self.text_location,
+            replaced: 0,
+            start: self.text_location,
+            wrapped: false,
+        });
+        self.find_next_replacement(self.text_location)
+    }
+    /// Replaces the current match and moves on to the next one.
+    /// Returns `false` once there are no more matches.
+    pub fn replace_current(&mut self) -> bool {
+        let Some(replace_info) = &mut self.replace_info else {
+            return false;
+        };
+        let at = self.text_location;
+        let match_len = self
+            .buffer
+            .borrow()
+            .lines
+            .get(at.line_index)
+            .and_then(|line| line.match_len(at.grapheme_index, &replace_info.query))
+            .unwrap_or(0);
+        let Some(replacement_len) = self.buffer.borrow_mut().replace_match(
+            at,
+            &replace_info.query,
+            &replace_info.replacement,
+        ) else {
+            return false;
+        };
+        replace_info.replaced = replace_info.replaced.saturating_add(1);
+        // Replacing a match in front of the start on its line shifts the start.
+        if at.line_index == replace_info.start.line_index
+            && at.grapheme_index < replace_info.start.grapheme_index
+        {
+            replace_info.start.grapheme_index = replace_info
+                .start
+                .grapheme_index
+                .saturating_add(replacement_len)
+                .saturating_sub(match_len);
+        }
+        self.set_needs_redraw(true);
+        // Continue after the replacement, so it can never be matched again.
+        let from = Location {
+            line_index: self.text_location.line_index,
+            grapheme_index: self
+                .text_location
+                .grapheme_index
+                .saturating_add(replacement_len),
+        };
+        self.find_next_replacement(from)
+    }
+    /// Leaves the current match untouched and moves on to the next one.
+    /// Returns `false` once there are no more matches.
+    pub fn skip_current(&mut self) -> bool {
+        let from = Location {
+            line_index: self.text_location.line_index,
+            grapheme_index: self.text_location.grapheme_index.saturating_add(1),
+        };
+        self.find_next_replacement(from)
+    }
+    pub fn replace_all(&mut self) {
+        while self.replace_current() {}
+    }
+    /// Ends the replace session, turning all replacements into a single undo step.
+    /// Returns the number of replaced matches.
+    pub fn exit_replace(&mut self) -> usize {
+        let Some(replace_info) = self.replace_info.take() else {
+            return 0;
+        };
+        self.buffer.borrow_mut().commit_changes(
+            replace_info.prev_location,
+            self.text_location,
+            false,
+        );
+        replace_info.replaced
+    }
+    // Moves the caret to the next match at or after `from`, wrapping around at the end of the
+    // buffer once. Returns `false` when the walk is back where it started.
+    fn find_next_replacement(&mut self, from: Location) -> bool {
+        let Some(replace_info) = &mut self.replace_info else {
+            return false;
+        };
+        let Some(location) = self
+            .buffer
+            .borrow()
+            .search_forward(&replace_info.query, from)
+        else {
+            return false;
+        };
+        if location < from {
+            if replace_info.wrapped {
+                return false;
+            }
+            replace_info.wrapped = true;
+        }
+        if replace_info.wrapped && location >= replace_info.start {
+            return false;
+        }
+        self.text_location = location;
+        self.scroll_text_location_into_view();
+        // The highlighted match moved.
+        self.set_needs_redraw(true);
+        true
+    }
+    // While confirming replacements, the match in question is highlighted.
+    fn replace_match_annotation(&self, line_index: usize) -> Option<Annotation> {
+        let replace_info = self.replace_info.as_ref()?;
+        let Location {
+            grapheme_index,
+            line_index: match_line_index,
+        } = self.text_location;
+        if line_index != match_line_index {
+            return None;
+        }
+        let match_len = self
+            .buffer
+            .borrow()
+            .lines
+            .get(line_index)?
+            .match_len(grapheme_index, &replace_info.query)?;
+        Some(Annotation {
+            annotation_type: AnnotationType::Match,
+            range: grapheme_index..grapheme_index.saturating_add(match_len),
+        })
     }
 
     // endregion
 
     // region: command handling
     pub fn handle_edit_command(&mut self, command: Edit) {
+        let caret_before = self.text_location;
+        // Consecutive typing of non-whitespace characters is undone as a single step.
+        let coalesce = self.selection().is_none()
+            && matches!(command, Edit::Insert(character) if !character.is_whitespace());
         match command {
-            Edit::Insert(character) => self.insert_char(character),
-            Edit::Delete => self.delete(),
-            Edit::DeleteBackward => self.delete_backward(),
-            Edit::InsertNewline => self.insert_newline(),
+            Edit::Insert(character) => {
+                self.delete_selection();
+                self.insert_char(character);
+            }
+            Edit::InsertText(text) => {
+                self.delete_selection();
+                self.insert_text(&text);
+            }
+            Edit::InsertNewline => {
+                self.delete_selection();
+                self.insert_newline();
+            }
+            Edit::Delete => {
+                if !self.delete_selection() {
+                    self.delete();
+                }
+            }
+            Edit::DeleteBackward => {
+                if !self.delete_selection() && !self.delete_indentation_backward() {
+                    self.delete_backward();
+                }
+            }
+            Edit::Indent => self.indent(),
+            Edit::Dedent => self.dedent(),
+            Edit::Undo => {
+                self.clear_selection();
+                self.undo();
+            }
+            Edit::Redo => {
+                self.clear_selection();
+                self.redo();
+            }
         }
+        self.buffer
+            .borrow_mut()
+            .commit_changes(caret_before, self.text_location, coalesce);
     }
     pub fn handle_move_command(&mut self, command: Move) {
+        self.buffer.borrow_mut().seal_history();
+        self.clear_selection();
+        self.move_text_location(command);
+    }
+    /// Moves the caret while extending the selection, starting a new one if necessary.
+    pub fn handle_select_command(&mut self, command: Move) {
+        self.buffer.borrow_mut().seal_history();
+        if self.selection_anchor.is_none() {
+            self.selection_anchor = Some(self.text_location);
+        }
+        self.move_text_location(command);
+        self.set_needs_redraw(true);
+    }
+    fn move_text_location(&mut self, command: Move) {
         let Size { height, .. } = self.size;
+        let previous_line_index = self.text_location.line_index;
         // This match moves the positon, but does not check for all boundaries.
         // The final boundarline checking happens after the match statement.
         match command {
-            Move::Up => self.move_up(1),
-            Move::Down => self.move_down(1),
+            Move::Up => self.move_rows_up(1),
+            Move::Down => self.move_rows_down(1),
             Move::Left => self.move_left(),
             Move::Right => self.move_right(),
-            Move::PageUp => self.move_up(height.saturating_sub(1)),
-            Move::PageDown => self.move_down(height.saturating_sub(1)),
+            Move::PageUp => self.move_rows_up(height.saturating_sub(1)),
+            Move::PageDown => self.move_rows_down(height.saturating_sub(1)),
             Move::StartOfLine => self.move_to_start_of_line(),
             Move::EndOfLine => self.move_to_end_of_line(),
         }
         self.scroll_text_location_into_view();
+        // The gutter highlights the caret line, and relative numbers depend on it.
+        if self.line_numbers != LineNumbers::Off
+            && self.text_location.line_index != previous_line_index
+        {
+            self.set_needs_redraw(true);
+        }
     }
 
     // endregion
     // region: Text editing
     fn insert_newline(&mut self) {
-        self.buffer.insert_newline(self.text_location);
-        self.handle_move_command(Move::Right);
+        let Location {
+            grapheme_index,
+            line_index,
+        } = self.text_location;
+        let in_leading_whitespace =
+            self.buffer
+                .borrow()
+                .lines
+                .get(line_index)
+                .is_some_and(|line| {
+                    line.substr(0..grapheme_index)
+                        .chars()
+                        .all(|character| character == ' ' || character == '\t')
+                });
+        // Breaking the line in front of its indentation moves the whitespace left of the caret
+        // to the new line, instead of copying it there and leaving it behind on the old line.
+        if in_leading_whitespace {
+            self.buffer.borrow_mut().insert_newline(Location {
+                grapheme_index: 0,
+                line_index,
+            });
+            self.jump_to(Location {
+                grapheme_index,
+                line_index: line_index.saturating_add(1),
+            });
+            return;
+        }
+        let indentation = self.indentation_for_new_line();
+        self.buffer.borrow_mut().insert_newline(self.text_location);
+        self.move_text_location(Move::Right);
+        if !indentation.is_empty() {
+            self.insert_text(&indentation);
+        }
         self.set_needs_redraw(true);
     }
+    // A new line keeps the indentation of the caret line, and is indented one level deeper
+    // if the text before the caret ends in a character that opens a block for this file type.
+    fn indentation_for_new_line(&self) -> String {
+        let buffer = self.buffer.borrow();
+        let Some(line) = buffer.lines.get(self.text_location.line_index) else {
+            return String::new();
+        };
+        let before_caret = line.substr(0..self.text_location.grapheme_index);
+        let mut indentation: String = before_caret
+            .chars()
+            .take_while(|&character| character == ' ' || character == '\t')
+            .collect();
+        let triggers = buffer.file_info.get_file_type().indent_triggers();
+        if before_caret
+            .trim_end()
+            .ends_with(|character| triggers.contains(&character))
+        {
+            indentation.push_str(&self.indentation().unit());
+        }
+        indentation
+    }
     fn delete_backward(&mut self) {
         if self.text_location.line_index != 0 || self.text_location.grapheme_index != 0 {
-            self.handle_move_command(Move::Left);
+            self.move_text_location(Move::Left);
             self.delete();
         }
     }
     fn delete(&mut self) {
-        self.buffer.delete(self.text_location);
+        self.buffer.borrow_mut().delete(self.text_location);
         self.set_needs_redraw(true);
     }
+    // Inserts text that may span multiple lines (e.g. pasted text) in one go.
+    // Windows (CRLF) and old Mac (CR) line breaks are normalized to newlines.
+    fn insert_text(&mut self, text: &str) {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        let end = self
+            .buffer
+            .borrow_mut()
+            .insert_text(self.text_location, &text);
+        self.jump_to(end);
+    }
     fn insert_char(&mut self, character: char) {
         let old_len = self
             .buffer
+            .borrow()
             .lines
             .get(self.text_location.line_index)
             .map_or(0, Line::grapheme_count);
-        self.buffer.insert_char(character, self.text_location);
+        self.buffer
+            .borrow_mut()
+            .insert_char(character, self.text_location);
         let new_len = self
             .buffer
+            .borrow()
             .lines
             .get(self.text_location.line_index)
             .map_or(0, Line::grapheme_count);
         let grapheme_delta = new_len.saturating_sub(old_len);
         if grapheme_delta > 0 {
             //move right for an added grapheme (should be the regular case)
-            self.handle_move_command(Move::Right);
+            self.move_text_location(Move::Right);
+        }
+        self.set_needs_redraw(true);
+    }
+    fn undo(&mut self) {
+        let location = self.buffer.borrow_mut().undo();
+        if let Some(location) = location {
+            self.jump_to(location);
+        }
+    }
+    fn redo(&mut self) {
+        let location = self.buffer.borrow_mut().redo();
+        if let Some(location) = location {
+            self.jump_to(location);
         }
+    }
+    fn jump_to(&mut self, location: Location) {
+        self.text_location = location;
+        self.snap_to_valid_line();
+        self.snap_to_valid_grapheme();
+        self.scroll_text_location_into_view();
         self.set_needs_redraw(true);
     }
     // endregion
 
+    // region: indentation
+
+    pub fn set_indentation(&mut self, indentation: Indentation) {
+        self.default_indentation = indentation;
+    }
+    fn indentation(&self) -> Indentation {
+        self.buffer
+            .borrow()
+            .indentation
+            .unwrap_or(self.default_indentation)
+    }
+    // Returns the lines covered by a selection spanning multiple lines. A selection ending at the
+    // start of a line doesn't cover that line.
+    fn selected_lines(&self) -> Option<Range<usize>> {
+        let (start, end) = self
+            .selection()
+            .filter(|(start, end)| start.line_index != end.line_index)?;
+        let end_line_index = if end.grapheme_index == 0 {
+            end.line_index
+        } else {
+            end.line_index.saturating_add(1)
+        };
+        Some(start.line_index..end_line_index.min(self.buffer.borrow().height()))
+    }
+    // Indents all selected lines, or inserts indentation up to the next indent stop at the caret.
+    fn indent(&mut self) {
+        if let Some(lines) = self.selected_lines() {
+            let unit = self.indentation().unit();
+            let unit_len = Line::from(&unit).grapheme_count();
+            for line_index in lines {
+                self.buffer.borrow_mut().insert_text(
+                    Location {
+                        line_index,
+                        grapheme_index: 0,
+                    },
+                    &unit,
+                );
+                self.shift_locations_on_line(line_index, |grapheme_index| {
+                    grapheme_index.saturating_add(unit_len)
+                });
+            }
+            self.scroll_text_location_into_view();
+            self.set_needs_redraw(true);
+            return;
+        }
+        self.delete_selection();
+        let text = match self.indentation() {
+            Indentation::Tabs => self.indentation().unit(),
+            Indentation::Spaces(width) => {
+                let col = self
+                    .buffer
+                    .borrow()
+                    .lines
+                    .get(self.text_location.line_index)
+                    .map_or(0, |line| {
+                        line.width_until(self.text_location.grapheme_index, self.tab_stops)
+                    });
+                " ".repeat(width.saturating_sub(col.checked_rem(width).unwrap_or(0)))
+            }
+        };
+        self.insert_text(&text);
+    }
+    // Removes one level of indentation, i.e. a tab or up to one indent width of spaces,
+    // from all selected lines or from the caret line.
+    fn dedent(&mut self) {
+        let lines = self.selected_lines().unwrap_or_else(|| {
+            let line_index = self.text_location.line_index;
+            line_index
+                ..line_index
+                    .saturating_add(1)
+                    .min(self.buffer.borrow().height())
+        });
+        let width = self.indentation().width(self.tab_stops.width);
+        for line_index in lines {
+            let count = self
+                .buffer
+                .borrow()
+                .lines
+                .get(line_index)
+                .map_or(0, |line| {
+                    let graphemes = line.graphemes();
+                    if graphemes.first() == Some(&"\t") {
+                        1
+                    } else {
+                        graphemes
+                            .iter()
+                            .take(width)
+                            .take_while(|&&grapheme| grapheme == " ")
+                            .count()
+                    }
+                });
+            if count == 0 {
+                continue;
+            }
+            self.buffer.borrow_mut().delete_range(
+                Location {
+                    line_index,
+                    grapheme_index: 0,
+                },
+                Location {
+                    line_index,
+                    grapheme_index: count,
+                },
+            );
+            self.shift_locations_on_line(line_index, |grapheme_index| {
+                grapheme_index.saturating_sub(count)
+            });
+        }
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+    // Keeps the caret and the selection anchor on the same text after graphemes were inserted
+    // at or removed from the start of the given line.
+    fn shift_locations_on_line(&mut self, line_index: usize, shift: impl Fn(usize) -> usize) {
+        for location in self
+            .selection_anchor
+            .iter_mut()
+            .chain(iter::once(&mut self.text_location))
+        {
+            if location.line_index == line_index {
+                location.grapheme_index = shift(location.grapheme_index);
+            }
+        }
+    }
+    // Backspace within leading spaces removes everything back to the previous indent stop.
+    // Returns whether it did so.
+    fn delete_indentation_backward(&mut self) -> bool {
+        let Indentation::Spaces(width) = self.indentation() else {
+            return false;
+        };
+        let Location {
+            line_index,
+            grapheme_index,
+        } = self.text_location;
+        let is_in_leading_spaces = grapheme_index > 0
+            && self
+                .buffer
+                .borrow()
+                .lines
+                .get(line_index)
+                .is_some_and(|line| {
+                    line.substr(0..grapheme_index)
+                        .chars()
+                        .all(|character| character == ' ')
+                });
+        if !is_in_leading_spaces {
+            return false;
+        }
+        let count = grapheme_index
+            .saturating_sub(1)
+            .checked_rem(width)
+            .unwrap_or(0)
+            .saturating_add(1);
+        let start = Location {
+            line_index,
+            grapheme_index: grapheme_index.saturating_sub(count),
+        };
+        self.buffer
+            .borrow_mut()
+            .delete_range(start, self.text_location);
+        self.jump_to(start);
+        true
+    }
+
+    // endregion
+
+    // region: selection
+
+    /// Returns the start and end of the selection in text order, or `None` if nothing is selected.
+    fn selection(&self) -> Option<(Location, Location)> {
+        let anchor = self.selection_anchor?;
+        match anchor.cmp(&self.text_location) {
+            Ordering::Less => Some((anchor, self.text_location)),
+            Ordering::Greater => Some((self.text_location, anchor)),
+            Ordering::Equal => None,
+        }
+    }
+    fn clear_selection(&mut self) {
+        if self.selection_anchor.take().is_some() {
+            self.set_needs_redraw(true);
+        }
+    }
+    // Deletes the selected text and places the caret where the selection started.
+    // Returns `false` if nothing was selected.
+    fn delete_selection(&mut self) -> bool {
+        let Some((start, end)) = self.selection() else {
+            self.clear_selection();
+            return false;
+        };
+        self.buffer.borrow_mut().delete_range(start, end);
+        self.selection_anchor = None;
+        self.jump_to(start);
+        true
+    }
+    fn selection_or_current_line(&self) -> Option<(Location, Location)> {
+        self.selection().or_else(|| {
+            let line_index = self.text_location.line_index;
+            (line_index < self.buffer.borrow().height()).then_some((
+                Location {
+                    line_index,
+                    grapheme_index: 0,
+                },
+                Location {
+                    line_index: line_index.saturating_add(1),
+                    grapheme_index: 0,
+                },
+            ))
+        })
+    }
+    fn selection_annotation(&self, line_index: usize) -> Option<Annotation> {
+        let (start, end) = self.selection()?;
+        if line_index < start.line_index || line_index > end.line_index {
+            return None;
+        }
+        let from = if line_index == start.line_index {
+            start.grapheme_index
+        } else {
+            0
+        };
+        let to = if line_index == end.line_index {
+            end.grapheme_index
+        } else {
+            usize::MAX
+        };
+        Some(Annotation {
+            annotation_type: AnnotationType::Selection,
+            range: from..to,
+        })
+    }
+
+    // endregion
+
+    // region: clipboard
+
+    /// Returns the selected text, or the current line including its newline if nothing is selected.
+    pub fn copy_selection(&self) -> Option<String> {
+        let (start, end) = self.selection_or_current_line()?;
+        Some(self.buffer.borrow().text_range(start, end))
+    }
+    /// Like `copy_selection`, but also removes the returned text from the buffer.
+    pub fn cut_selection(&mut self) -> Option<String> {
+        let (start, end) = self.selection_or_current_line()?;
+        let caret_before = self.text_location;
+        let text = self.buffer.borrow().text_range(start, end);
+        self.buffer.borrow_mut().delete_range(start, end);
+        self.selection_anchor = None;
+        self.jump_to(start);
+        self.buffer
+            .borrow_mut()
+            .commit_changes(caret_before, self.text_location, false);
+        Some(text)
+    }
+
+    // endregion
+
     // region: Rendering
 
-    fn render_line(at: usize, line_text: &str) -> Result<(), Error> {
-        Terminal::print_row(at, line_text)
+    fn render_line(origin: Position, width: usize, line_text: &str) -> Result<(), Error> {
+        Terminal::print_row_part(origin, width, line_text)
     }
     fn build_welcome_message(width: usize) -> String {
         if width == 0 {
@@ -136,6 +908,208 @@ impl View {
         }
         format!("{:<1}{:^remaining_width$}", "~", welcome_message)
     }
+    pub fn set_tab_stops(&mut self, tab_stops: TabStops) {
+        self.tab_stops = tab_stops;
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+    pub fn set_line_numbers(&mut self, line_numbers: LineNumbers) {
+        self.line_numbers = line_numbers;
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+    // The gutter is wide enough for the highest line number, followed by a space.
+    fn gutter_width(&self) -> usize {
+        if self.line_numbers == LineNumbers::Off {
+            return 0;
+        }
+        self.buffer
+            .borrow()
+            .height()
+            .max(1)
+            .to_string()
+            .len()
+            .saturating_add(1)
+    }
+    fn text_width(&self) -> usize {
+        self.size.width.saturating_sub(self.gutter_width())
+    }
+    // Wrapped lines only show their number next to their first row.
+    fn build_gutter(&self, line_index: usize, is_first_row: bool) -> AnnotatedString {
+        let mut gutter = AnnotatedString::default();
+        let width = self.gutter_width();
+        if width == 0 {
+            return gutter;
+        }
+        if !is_first_row {
+            gutter.push(&" ".repeat(width), None);
+            return gutter;
+        }
+        let caret_line_index = self.text_location.line_index;
+        let number_width = width.saturating_sub(1);
+        let label = self.line_numbers.label(line_index, caret_line_index);
+        let annotation_type = if line_index == caret_line_index {
+            AnnotationType::CurrentLineNumber
+        } else {
+            AnnotationType::LineNumber
+        };
+        gutter.push(&format!("{label:>number_width$}"), Some(annotation_type));
+        gutter.push(" ", None);
+        gutter
+    }
+    // Returns the line index, the column range and whether it's the first row of its line
+    // for each row of text visible in the view.
+    fn visible_rows(&self) -> Vec<(usize, Range<usize>, bool)> {
+        let Size { height, .. } = self.size;
+        let text_width = self.text_width();
+        if !self.soft_wrap {
+            let left = self.scroll_offset.col;
+            return (self.scroll_offset.row..self.buffer.borrow().height())
+                .take(height)
+                .map(|line_index| (line_index, left..left.saturating_add(text_width), true))
+                .collect();
+        }
+        let top_line_index = self.scroll_offset.row;
+        let top_row = self.top_wrapped_row;
+        self.buffer
+            .borrow()
+            .lines
+            .iter()
+            .enumerate()
+            .skip(top_line_index)
+            .flat_map(|(line_index, line)| {
+                line.wrap(text_width, self.tab_stops)
+                    .into_iter()
+                    .enumerate()
+                    .map(move |(row, graphemes)| {
+                        let start = line.width_until(graphemes.start, self.tab_stops);
+                        // A grapheme wider than the view is clipped rather than overflowing it.
+                        let end = line
+                            .width_until(graphemes.end, self.tab_stops)
+                            .min(start.saturating_add(text_width));
+                        (line_index, start..end, row == 0)
+                    })
+            })
+            .skip(top_row)
+            .take(height)
+            .collect()
+    }
+    // endregion
+
+    // region: Soft wrap
+
+    pub const fn soft_wrap(&self) -> bool {
+        self.soft_wrap
+    }
+    pub fn set_soft_wrap(&mut self, soft_wrap: bool) {
+        self.soft_wrap = soft_wrap;
+        self.top_wrapped_row = 0;
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+    // Returns the grapheme ranges of the display rows the given line is broken into.
+    // Without soft wrap, and for the empty line behind the last one, that's a single row.
+    // clippy::single_range_in_vec_init: A single row spanning all graphemes is what we want here.
+    #[allow(clippy::single_range_in_vec_init)]
+    fn display_rows(&self, line_index: usize) -> Vec<Range<usize>> {
+        self.buffer.borrow().lines.get(line_index).map_or_else(
+            || vec![0..0],
+            |line| {
+                if self.soft_wrap {
+                    line.wrap(self.text_width(), self.tab_stops)
+                } else {
+                    vec![0..line.grapheme_count()]
+                }
+            },
+        )
+    }
+    // Returns the index of the display row the caret is on within its line, together with
+    // the caret's column in that row.
+    fn caret_display_row(&self) -> (usize, usize) {
+        let Location {
+            line_index,
+            grapheme_index,
+        } = self.text_location;
+        let rows = self.display_rows(line_index);
+        let row = rows
+            .iter()
+            .rposition(|graphemes| graphemes.start <= grapheme_index)
+            .unwrap_or(0);
+        let col = rows.get(row).map_or(0, |graphemes| {
+            self.buffer
+                .borrow()
+                .lines
+                .get(line_index)
+                .map_or(0, |line| {
+                    line.width_of(graphemes.start..grapheme_index, self.tab_stops)
+                })
+        });
+        (row, col)
+    }
+    // Places the caret at column `col` of the given display row of the given line.
+    fn move_to_display_row(&mut self, line_index: usize, row: usize, col: usize) {
+        let rows = self.display_rows(line_index);
+        let is_last_row = row.saturating_add(1) >= rows.len();
+        let grapheme_index = match (rows.get(row), self.buffer.borrow().lines.get(line_index)) {
+            (Some(graphemes), Some(line)) => {
+                let grapheme_index =
+                    line.grapheme_index_at_col(graphemes.clone(), col, self.tab_stops);
+                // The end of a row that isn't the last is the start of the next row,
+                // so the caret stays on the row's last grapheme instead.
+                if grapheme_index == graphemes.end && !is_last_row {
+                    grapheme_index.saturating_sub(1).max(graphemes.start)
+                } else {
+                    grapheme_index
+                }
+            }
+            _ => 0,
+        };
+        self.text_location = Location {
+            grapheme_index,
+            line_index,
+        };
+    }
+    fn move_rows_up(&mut self, step: usize) {
+        if !self.soft_wrap {
+            self.move_up(step);
+            return;
+        }
+        let (mut row, col) = self.caret_display_row();
+        let mut line_index = self.text_location.line_index;
+        for _ in 0..step {
+            if row > 0 {
+                row = row.saturating_sub(1);
+            } else if line_index > 0 {
+                line_index = line_index.saturating_sub(1);
+                row = self.display_rows(line_index).len().saturating_sub(1);
+            } else {
+                break;
+            }
+        }
+        self.move_to_display_row(line_index, row, col);
+    }
+    fn move_rows_down(&mut self, step: usize) {
+        if !self.soft_wrap {
+            self.move_down(step);
+            return;
+        }
+        let (mut row, col) = self.caret_display_row();
+        let mut line_index = self.text_location.line_index;
+        let mut row_count = self.display_rows(line_index).len();
+        for _ in 0..step {
+            if row.saturating_add(1) < row_count {
+                row = row.saturating_add(1);
+            } else if line_index < self.buffer.borrow().height() {
+                line_index = line_index.saturating_add(1);
+                row = 0;
+                row_count = self.display_rows(line_index).len();
+            } else {
+                break;
+            }
+        }
+        self.move_to_display_row(line_index, row, col);
+    }
+
     // endregion
 
     // region: Scrolling
@@ -156,7 +1130,7 @@ impl View {
         }
     }
     fn scroll_horizontally(&mut self, to: usize) {
-        let Size { width, .. } = self.size;
+        let width = self.text_width();
         let offset_changed = if to < self.scroll_offset.col {
             self.scroll_offset.col = to;
             true
@@ -170,7 +1144,71 @@ impl View {
             self.set_needs_redraw(true);
         }
     }
+    // With soft wrap, only the lines between the top of the view and the caret are wrapped.
+    fn scroll_wrapped_text_location_into_view(&mut self) {
+        let height = self.size.height;
+        let line_index = self.text_location.line_index;
+        let (row, _) = self.caret_display_row();
+        let old_offset = (self.scroll_offset.row, self.top_wrapped_row);
+        // Edits and resizes can leave the top row past the end of its line.
+        let top_row_count = self.display_rows(self.scroll_offset.row).len();
+        self.top_wrapped_row = self.top_wrapped_row.min(top_row_count.saturating_sub(1));
+        match self.rows_from_top(line_index, row, height) {
+            None => {
+                self.scroll_offset.row = line_index;
+                self.top_wrapped_row = row;
+            }
+            Some(rows) if rows >= height => {
+                // Scroll so that the caret ends up on the last row.
+                let (mut top_line_index, mut top_row) = (line_index, row);
+                for _ in 1..height {
+                    if top_row > 0 {
+                        top_row = top_row.saturating_sub(1);
+                    } else if top_line_index > 0 {
+                        top_line_index = top_line_index.saturating_sub(1);
+                        top_row = self.display_rows(top_line_index).len().saturating_sub(1);
+                    } else {
+                        break;
+                    }
+                }
+                self.scroll_offset.row = top_line_index;
+                self.top_wrapped_row = top_row;
+            }
+            Some(_) => {}
+        }
+        if (self.scroll_offset.row, self.top_wrapped_row) != old_offset {
+            self.set_needs_redraw(true);
+        }
+    }
+    // Counts the display rows from the top of the view down to the given row of the given
+    // line, or returns `None` if that row is above the view. Counting stops once it passes
+    // `limit`, as everything from there on is below the view anyway.
+    fn rows_from_top(&self, line_index: usize, row: usize, limit: usize) -> Option<usize> {
+        let top_line_index = self.scroll_offset.row;
+        let top_row = self.top_wrapped_row;
+        if (line_index, row) < (top_line_index, top_row) {
+            return None;
+        }
+        if line_index == top_line_index {
+            return Some(row.saturating_sub(top_row));
+        }
+        let mut rows = self
+            .display_rows(top_line_index)
+            .len()
+            .saturating_sub(top_row);
+        for line_above in top_line_index.saturating_add(1)..line_index {
+            if rows > limit {
+                return Some(rows);
+            }
+            rows = rows.saturating_add(self.display_rows(line_above).len());
+        }
+        Some(rows.saturating_add(row))
+    }
     fn scroll_text_location_into_view(&mut self) {
+        if self.soft_wrap {
+            self.scroll_wrapped_text_location_into_view();
+            return;
+        }
         let Position { row, col } = self.text_location_to_position();
         self.scroll_vertically(row);
         self.scroll_horizontally(col);
@@ -181,16 +1219,37 @@ impl View {
     // region: Location and Position Handling
 
     pub fn caret_position(&self) -> Position {
-        self.text_location_to_position()
-            .saturating_sub(self.scroll_offset)
+        let Position { row, col } = if self.soft_wrap {
+            let (row, col) = self.caret_display_row();
+            let row = self
+                .rows_from_top(self.text_location.line_index, row, self.size.height)
+                .unwrap_or_default();
+            Position { col, row }
+        } else {
+            self.text_location_to_position()
+                .saturating_sub(self.scroll_offset)
+        };
+        Position {
+            row,
+            col: col.saturating_add(self.gutter_width()),
+        }
     }
 
+    // Only used without soft wrap, where each line is a single row.
     fn text_location_to_position(&self) -> Position {
-        let row = self.text_location.line_index;
-        let col = self.buffer.lines.get(row).map_or(0, |line| {
-            line.width_until(self.text_location.grapheme_index)
-        });
-        Position { col, row }
+        let line_index = self.text_location.line_index;
+        let col = self
+            .buffer
+            .borrow()
+            .lines
+            .get(line_index)
+            .map_or(0, |line| {
+                line.width_until(self.text_location.grapheme_index, self.tab_stops)
+            });
+        Position {
+            col,
+            row: line_index,
+        }
     }
 
     // endregion
@@ -212,6 +1271,7 @@ impl View {
     fn move_right(&mut self) {
         let line_width = self
             .buffer
+            .borrow()
             .lines
             .get(self.text_location.line_index)
             .map_or(0, Line::grapheme_count);
@@ -239,6 +1299,7 @@ impl View {
     fn move_to_end_of_line(&mut self) {
         self.text_location.grapheme_index = self
             .buffer
+            .borrow()
             .lines
             .get(self.text_location.line_index)
             .map_or(0, Line::grapheme_count);
@@ -249,6 +1310,7 @@ impl View {
     fn snap_to_valid_grapheme(&mut self) {
         self.text_location.grapheme_index = self
             .buffer
+            .borrow()
             .lines
             .get(self.text_location.line_index)
             .map_or(0, |line| {
@@ -258,7 +1320,8 @@ impl View {
     // Ensures self.location.line_index points to a valid line index by snapping it to the bottom most line if appropriate.
     // Doesn't trigger scrolling.
     fn snap_to_valid_line(&mut self) {
-        self.text_location.line_index = min(self.text_location.line_index, self.buffer.height());
+        self.text_location.line_index =
+            min(self.text_location.line_index, self.buffer.borrow().height());
     }
 
     // endregion
@@ -270,38 +1333,256 @@ impl UIComponent for View {
     }
 
     fn needs_redraw(&self) -> bool {
-        self.needs_redraw
+        self.needs_redraw || self.buffer.borrow().revision() != self.drawn_revision
     }
     fn set_size(&mut self, size: Size) {
         self.size = size;
         self.scroll_text_location_into_view();
     }
 
-    fn draw(&mut self, origin_row: usize) -> Result<(), Error> {
+    fn draw(&mut self, origin: Position) -> Result<(), Error> {
         let Size { height, width } = self.size;
-        let end_y = origin_row.saturating_add(height);
+        let end_y = origin.row.saturating_add(height);
         // we allow this since we don't care if our welcome message is put _exactly_ in the top third.
         // it's allowed to be a bit too far up or down
         #[allow(clippy::integer_division)]
-        let top_third = height / 3;
-        let scroll_top = self.scroll_offset.row;
-        for current_row in origin_row..end_y {
-            // to get the correct line index, we have to take current_row (the absolute row on screen),
-            // subtract origin_row to get the current row relative to the view (ranging from 0 to self.size.height)
-            // and add the scroll offset.
-            let line_idx = current_row
-                .saturating_sub(origin_row)
-                .saturating_add(scroll_top);
-            if let Some(line) = self.buffer.lines.get(line_idx) {
-                let left = self.scroll_offset.col;
-                let right = self.scroll_offset.col.saturating_add(width);
-                Self::render_line(current_row, &line.get_visible_graphemes(left..right))?;
-            } else if current_row == top_third && self.buffer.is_empty() {
-                Self::render_line(current_row, &Self::build_welcome_message(width))?;
+        let top_third = origin.row.saturating_add(height / 3);
+        let visible_rows = self.visible_rows();
+        if let Some((last_line_index, _, _)) = visible_rows.last() {
+            self.buffer.borrow_mut().update_highlights(*last_line_index);
+        }
+        let buffer = self.buffer.borrow();
+        for current_row in origin.row..end_y {
+            let row_origin = Position {
+                row: current_row,
+                col: origin.col,
+            };
+            // to get the correct row of text, we have to take current_row (the absolute row on screen)
+            // and subtract origin.row to get the current row relative to the view (ranging from 0 to self.size.height).
+            let visible_row = visible_rows
+                .get(current_row.saturating_sub(origin.row))
+                .and_then(|(line_idx, columns, is_first_row)| {
+                    buffer
+                        .lines
+                        .get(*line_idx)
+                        .map(|line| (*line_idx, line, columns.clone(), *is_first_row))
+                });
+            if let Some((line_idx, line, columns, is_first_row)) = visible_row {
+                let mut annotations = buffer.syntax_annotations(line_idx).to_vec();
+                annotations.extend(self.selection_annotation(line_idx));
+                annotations.extend(self.replace_match_annotation(line_idx));
+                let mut row = self.build_gutter(line_idx, is_first_row);
+                row.append(&line.get_annotated_visible_graphemes(
+                    columns,
+                    &annotations,
+                    self.tab_stops,
+                ));
+                Terminal::print_annotated_row(row_origin, width, &row)?;
+            } else if current_row == top_third && buffer.is_empty() {
+                Self::render_line(row_origin, width, &Self::build_welcome_message(width))?;
             } else {
-                Self::render_line(current_row, "~")?;
+                Self::render_line(row_origin, width, "~")?;
             }
         }
+        self.drawn_revision = buffer.revision();
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AnnotationType, Buffer, Edit, Line, LineNumbers, Location, Move, Position, Rc, RefCell,
+        SearchQuery, Size, UIComponent, View,
+    };
+    use crate::editor::searchquery::SearchMode;
+
+    // A view without line numbers, so the whole width is text.
+    fn view(text: &str, width: usize, height: usize, soft_wrap: bool) -> View {
+        let mut buffer = Buffer::default();
+        buffer.lines = text.lines().map(Line::from).collect();
+        let mut view = View {
+            buffer: Rc::new(RefCell::new(buffer)),
+            line_numbers: LineNumbers::Off,
+            soft_wrap,
+            ..View::default()
+        };
+        view.set_size(Size { height, width });
+        view
+    }
+
+    fn wrapped_view(text: &str, width: usize, height: usize) -> View {
+        view(text, width, height, true)
+    }
+
+    fn lines(view: &View) -> Vec<String> {
+        view.buffer
+            .borrow()
+            .lines
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    fn caret(view: &View) -> (usize, usize) {
+        let Position { row, col } = view.caret_position();
+        (row, col)
+    }
+
+    fn move_caret(view: &mut View, command: Move, times: usize) {
+        for _ in 0..times {
+            view.handle_move_command(command);
+        }
+    }
+
+    #[test]
+    fn moves_through_rows_broken_before_a_wide_grapheme() {
+        // The rows are "ab日", "本cd" and the empty row behind the full last one.
+        let mut view = wrapped_view("ab日本cd", 4, 5);
+        move_caret(&mut view, Move::Right, 2);
+        assert_eq!(caret(&view), (0, 2));
+        view.handle_move_command(Move::Down);
+        // Column 2 of the second row is the "c" behind "本".
+        assert_eq!(view.text_location.grapheme_index, 4);
+        assert_eq!(caret(&view), (1, 2));
+        view.handle_move_command(Move::Down);
+        assert_eq!(view.text_location.grapheme_index, 6);
+        assert_eq!(caret(&view), (2, 0));
+    }
+
+    #[test]
+    fn moves_through_rows_broken_before_a_tab() {
+        // The tab doesn't fit behind "ab", so the rows are "ab", "\tx" and an empty one.
+        let mut view = wrapped_view("ab\tx", 3, 5);
+        move_caret(&mut view, Move::Right, 3);
+        assert_eq!(caret(&view), (1, 2));
+        // Above, column 2 is behind the end of the row, so the caret stays on its last grapheme.
+        view.handle_move_command(Move::Up);
+        assert_eq!(view.text_location.grapheme_index, 1);
+        assert_eq!(caret(&view), (0, 1));
+    }
+
+    #[test]
+    fn a_zero_width_view_keeps_each_line_on_one_row() {
+        let mut view = wrapped_view("abc\nd", 0, 2);
+        move_caret(&mut view, Move::Right, 2);
+        assert_eq!(caret(&view), (0, 2));
+        assert_eq!(view.visible_rows(), vec![(0, 0..0, true), (1, 0..0, true)]);
+        view.handle_move_command(Move::Down);
+        assert_eq!(caret(&view), (1, 1));
+    }
+
+    #[test]
+    fn toggling_soft_wrap_keeps_both_scroll_offsets_apart() {
+        // The rows are "aaa ", "bbb " and "ccc", of which only two fit.
+        let mut view = wrapped_view("aaa bbb ccc", 4, 2);
+        view.handle_move_command(Move::EndOfLine);
+        assert_eq!(caret(&view), (1, 3));
+        assert_eq!(
+            view.visible_rows(),
+            vec![(0, 4..8, false), (0, 8..11, false)]
+        );
+
+        view.set_soft_wrap(false);
+        assert_eq!((view.scroll_offset.row, view.scroll_offset.col), (0, 8));
+        assert_eq!(caret(&view), (0, 3));
+
+        view.set_soft_wrap(true);
+        assert_eq!(view.top_wrapped_row, 1);
+        assert_eq!(caret(&view), (1, 3));
+
+        // Moving within the wrapped rows doesn't touch the horizontal offset.
+        view.handle_move_command(Move::StartOfLine);
+        assert_eq!(caret(&view), (0, 0));
+        view.set_soft_wrap(false);
+        assert_eq!((view.scroll_offset.row, view.scroll_offset.col), (0, 0));
+    }
+
+    #[test]
+    fn a_new_line_keeps_the_indentation_left_of_the_caret() {
+        let mut view = view("  ab", 20, 5, false);
+        move_caret(&mut view, Move::Right, 3);
+        view.handle_edit_command(Edit::InsertNewline);
+        assert_eq!(lines(&view), vec!["  a", "  b"]);
+        assert_eq!(caret(&view), (1, 2));
+    }
+
+    #[test]
+    fn a_new_line_within_the_indentation_doesnt_duplicate_it() {
+        let mut view = view("    foo", 20, 5, false);
+        move_caret(&mut view, Move::Right, 2);
+        view.handle_edit_command(Edit::InsertNewline);
+        assert_eq!(lines(&view), vec!["", "    foo"]);
+        assert_eq!(caret(&view), (1, 2));
+        view.handle_edit_command(Edit::InsertNewline);
+        assert_eq!(lines(&view), vec!["", "", "    foo"]);
+        assert_eq!(caret(&view), (2, 2));
+    }
+
+    fn enter_replace(view: &mut View, query: &str, replacement: &str) -> bool {
+        view.enter_replace(
+            SearchQuery::new(query, SearchMode::Literal).unwrap(),
+            replacement,
+        )
+    }
+
+    fn location(view: &View) -> (usize, usize) {
+        let Location {
+            grapheme_index,
+            line_index,
+        } = view.text_location;
+        (line_index, grapheme_index)
+    }
+
+    #[test]
+    fn replace_starts_at_the_caret_and_wraps_around_once() {
+        let mut view = view("a x\nx b x", 20, 5, false);
+        view.handle_move_command(Move::Down);
+        move_caret(&mut view, Move::Right, 2);
+        assert!(enter_replace(&mut view, "x", "yy"));
+        assert_eq!(location(&view), (1, 4));
+        assert!(view.skip_current());
+        assert_eq!(location(&view), (0, 2));
+        assert!(view.replace_current());
+        assert_eq!(location(&view), (1, 0));
+        // The next match is the one skipped first, where the walk started.
+        assert!(!view.skip_current());
+        assert_eq!(view.exit_replace(), 1);
+        assert_eq!(lines(&view), vec!["a yy", "x b x"]);
+    }
+
+    #[test]
+    fn replace_all_covers_the_matches_before_the_caret() {
+        let mut view = view("x x\nx", 20, 5, false);
+        view.handle_move_command(Move::Down);
+        assert!(enter_replace(&mut view, "x", "xx"));
+        view.replace_all();
+        assert_eq!(view.exit_replace(), 3);
+        assert_eq!(lines(&view), vec!["xx xx", "xx"]);
+    }
+
+    #[test]
+    fn replacing_in_front_of_the_start_keeps_skipped_matches_skipped() {
+        let mut view = view("ab ab", 20, 5, false);
+        move_caret(&mut view, Move::Right, 3);
+        assert!(enter_replace(&mut view, "ab", ""));
+        assert!(view.skip_current());
+        assert_eq!(location(&view), (0, 0));
+        // The skipped match moved to where the walk would otherwise still look for matches.
+        assert!(!view.replace_current());
+        assert_eq!(view.exit_replace(), 1);
+        assert_eq!(lines(&view), vec![" ab"]);
+    }
+
+    #[test]
+    fn highlights_the_match_to_confirm() {
+        let mut view = view("a\nb foo c", 20, 5, false);
+        assert!(enter_replace(&mut view, "foo", "x"));
+        assert!(view.replace_match_annotation(0).is_none());
+        let annotation = view.replace_match_annotation(1).unwrap();
+        assert_eq!(annotation.annotation_type, AnnotationType::Match);
+        assert_eq!(annotation.range, 2..5);
+        view.exit_replace();
+        assert!(view.replace_match_annotation(1).is_none());
+    }
+}
@@ -1,58 +1,347 @@
-use std::fs::{read_to_string, File};
-use std::io::Error;
-use std::io::Write;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Error, ErrorKind, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process;
 
+use super::diff::{self, DiffLine};
+use super::highlighter::{self, Highlights};
+use super::history::{Change, History};
+use super::Annotation;
+use super::Encoding;
 use super::FileInfo;
+use super::FileStamp;
+use super::Indentation;
 use super::Line;
+use super::LineEnding;
 use super::Location;
+use super::SearchQuery;
+use super::SwapFile;
 
 #[derive(Default)]
 pub struct Buffer {
     pub lines: Vec<Line>,
     pub file_info: FileInfo,
     pub dirty: bool,
+    // The indentation the file uses, as detected on load. `None` if it has no indented lines.
+    pub indentation: Option<Indentation>,
+    pub encoding: Encoding,
+    pub line_ending: LineEnding,
+    // Whether the file's last line lacks a line ending. Lines are written back the same way.
+    pub missing_final_newline: bool,
+    saved_line_ending: LineEnding,
+    history: History,
+    highlights: Highlights,
+    swap_file: SwapFile,
+    // Counts the changes to the text, so views can tell whether they show the latest one.
+    revision: usize,
 }
 
-
 impl Buffer {
     pub fn load(file_name: &str) -> Result<Self, Error> {
-        let contents = read_to_string(file_name)?;
+        let bytes = fs::read(file_name)?;
+        let (encoding, contents) = Encoding::detect_and_decode(&bytes);
+        let mut file_info = FileInfo::from(file_name);
+        file_info.set_stamp(Some(FileStamp::new(Path::new(file_name), &bytes)));
+        Ok(Self::from_contents(file_info, &contents, encoding))
+    }
+    /// Reads the file again, decoding it as `encoding` instead of the detected encoding.
+    pub fn reload_as(&mut self, encoding: Encoding) -> Result<(), Error> {
+        let Some(file_path) = self.file_info.get_path() else {
+            return Err(Error::new(ErrorKind::NotFound, "the buffer has no file"));
+        };
+        let bytes = fs::read(file_path)?;
+        let contents = encoding.decode(&bytes).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("the file is not valid {encoding}"),
+            )
+        })?;
+        let stamp = FileStamp::new(file_path, &bytes);
+        let mut file_info = mem::take(&mut self.file_info);
+        file_info.set_stamp(Some(stamp));
+        let revision = self.revision.wrapping_add(1);
+        *self = Self::from_contents(file_info, &contents, encoding);
+        self.revision = revision;
+        Ok(())
+    }
+    /// Reads the file again, dropping all changes and the undo history.
+    pub fn reload(&mut self) -> Result<(), Error> {
+        self.reload_as(self.encoding)
+    }
+    pub fn is_changed_on_disk(&self) -> bool {
+        self.file_info.is_changed_on_disk()
+    }
+    /// Compares the text with the file on disk and returns the changes, or nothing if both
+    /// are the same.
+    pub fn diff_with_disk(&self) -> Result<Vec<DiffLine>, Error> {
+        let Some(file_path) = self.file_info.get_path() else {
+            return Ok(Vec::new());
+        };
+        let bytes = fs::read(file_path)?;
+        let contents = self
+            .encoding
+            .decode(&bytes)
+            .unwrap_or_else(|| Encoding::detect_and_decode(&bytes).1);
+        let on_disk = Self::from_contents(FileInfo::default(), &contents, self.encoding);
+        let ours: Vec<String> = self.lines.iter().map(Line::to_string).collect();
+        let theirs: Vec<String> = on_disk.lines.iter().map(Line::to_string).collect();
+        Ok(diff::diff_lines(&ours, &theirs))
+    }
+    fn from_contents(file_info: FileInfo, contents: &str, encoding: Encoding) -> Self {
+        let line_ending = LineEnding::detect(contents);
+        let missing_final_newline = !contents.is_empty() && !contents.ends_with('\n');
         let mut lines = Vec::new();
-        for value in contents.lines() {
-            lines.push(Line::from(value));
+        if !contents.is_empty() {
+            let contents = contents.strip_suffix('\n').unwrap_or(contents);
+            for value in contents.split('\n') {
+                // Only strip the carriage returns that belong to the line ending, so that
+                // stray ones survive a round trip.
+                let value = match line_ending {
+                    LineEnding::CrLf => value.strip_suffix('\r').unwrap_or(value),
+                    LineEnding::Lf => value,
+                };
+                lines.push(Line::from(value));
+            }
         }
-        Ok(Self {
+        Self {
+            highlights: Highlights::new(lines.len()),
+            indentation: Indentation::detect(&lines),
             lines,
-            file_info: FileInfo::from(file_name),
+            swap_file: SwapFile::for_file(file_info.get_path()),
+            file_info,
             dirty: false,
-        })
+            encoding,
+            line_ending,
+            missing_final_newline,
+            saved_line_ending: line_ending,
+            history: History::default(),
+            revision: 0,
+        }
     }
-    fn save_to_file(&self, file_info: &FileInfo) -> Result<(), Error> {
+    // Returns the stamp of the written file, or `None` if there is no file to write to.
+    fn save_to_file(&self, file_info: &FileInfo) -> Result<Option<FileStamp>, Error> {
         if let Some(file_path) = &file_info.get_path() {
-            let mut file = File::create(file_path)?;
-            for line in &self.lines {
-                writeln!(file, "{line}")?;
+            let line_ending = self.line_ending.as_str();
+            let last_index = self.lines.len().saturating_sub(1);
+            let mut contents = String::new();
+            for (line_index, line) in self.lines.iter().enumerate() {
+                contents.push_str(&line.to_string());
+                if line_index < last_index || !self.missing_final_newline {
+                    contents.push_str(line_ending);
+                }
             }
+            let bytes = self.encoding.encode(&contents).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("the text can't be encoded as {}", self.encoding),
+                )
+            })?;
+            write_atomically(file_path, &bytes)?;
+            return Ok(Some(FileStamp::new(file_path, &bytes)));
         }
-        Ok(())
+        Ok(None)
     }
     pub fn save_as(&mut self, file_name: &str) -> Result<(), Error> {
-        let file_info = FileInfo::from(file_name);
-        self.save_to_file(&file_info)?;
+        let mut file_info = FileInfo::from(file_name);
+        let stamp = self.save_to_file(&file_info)?;
+        file_info.set_stamp(stamp);
+        // The new name may come with a different file type.
+        if file_info.get_file_type() != self.file_info.get_file_type() {
+            self.highlights.clear();
+        }
+        self.swap_file.remove();
+        self.swap_file = SwapFile::for_file(file_info.get_path());
         self.file_info = file_info;
-        self.dirty = false;
+        self.mark_saved();
         Ok(())
     }
 
     pub fn save(&mut self) -> Result<(), Error> {
-        self.save_to_file(&self.file_info)?;
+        let stamp = self.save_to_file(&self.file_info)?;
+        self.file_info.set_stamp(stamp);
+        self.mark_saved();
+        Ok(())
+    }
+    fn mark_saved(&mut self) {
+        self.history.mark_saved();
+        self.saved_line_ending = self.line_ending;
         self.dirty = false;
+        self.swap_file.remove();
+    }
+    // The buffer is modified if its text or its line endings differ from the saved file.
+    fn update_dirty(&mut self) {
+        self.dirty =
+            !self.history.is_at_saved_state() || self.line_ending != self.saved_line_ending;
+    }
+    /// Checks whether the text can be saved in the buffer's encoding.
+    pub fn fits_encoding(&self) -> bool {
+        self.lines
+            .iter()
+            .all(|line| self.encoding.can_encode(&line.to_string()))
+    }
+    /// Switches to `encoding`, which takes effect on the next save.
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+    /// Switches to `line_ending`. Carriage returns kept at the end of lines from a file with
+    /// mixed line endings are removed as a separate undo step, so every line ends the same way.
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        for line_index in 0..self.lines.len() {
+            let Some(line) = self.lines.get_mut(line_index) else {
+                break;
+            };
+            let text = line.to_string();
+            if let Some(stripped) = text.strip_suffix('\r') {
+                *line = Line::from(stripped);
+                self.record_change(line_index, vec![text], 1);
+            }
+        }
+        self.commit_changes(Location::default(), Location::default(), false);
+        self.seal_history();
+        self.line_ending = line_ending;
+        self.update_dirty();
+    }
+
+    // region: swap file
+
+    /// Writes the unsaved text to the swap file if it changed and the last write is long
+    /// enough ago, or removes the swap file once there is nothing left to recover.
+    pub fn update_swap_file(&mut self) -> Result<(), Error> {
+        if !self.dirty {
+            self.swap_file.remove();
+            Ok(())
+        } else if self.swap_file.is_due() {
+            self.write_swap_file()
+        } else {
+            Ok(())
+        }
+    }
+    pub fn write_swap_file(&mut self) -> Result<(), Error> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let text = self
+            .lines
+            .iter()
+            .map(Line::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.swap_file.write(&text)
+    }
+    pub fn remove_swap_file(&mut self) {
+        self.swap_file.remove();
+    }
+    pub fn discard_swap_file(&mut self) {
+        self.swap_file.discard();
+    }
+    pub fn has_recoverable_swap_file(&self) -> bool {
+        self.file_info
+            .get_path()
+            .is_some_and(|file_path| self.swap_file.is_recoverable(file_path))
+    }
+    /// Replaces the text with the one kept in the swap file, as a single undoable change.
+    pub fn recover_swap_file(&mut self) -> Result<(), Error> {
+        let text = self.swap_file.recover()?;
+        let removed = self.lines.iter().map(Line::to_string).collect();
+        self.lines = text.split('\n').map(Line::from).collect();
+        self.record_change(0, removed, self.lines.len());
+        self.commit_changes(Location::default(), Location::default(), false);
+        self.seal_history();
         Ok(())
     }
 
+    // endregion
+
+    // region: search
+
+    /// Finds the next match of `query` at or after `from`, wrapping around at the end of the buffer.
+    pub fn search_forward(&self, query: &SearchQuery, from: Location) -> Option<Location> {
+        let mut is_first = true;
+        for (line_index, line) in self
+            .lines
+            .iter()
+            .enumerate()
+            .cycle()
+            .skip(from.line_index)
+            // The starting line is visited twice: once from `from` and once from its start.
+            .take(self.lines.len().saturating_add(1))
+        {
+            let from_grapheme_index = if is_first {
+                is_first = false;
+                from.grapheme_index
+            } else {
+                0
+            };
+            if let Some(grapheme_index) = line.search_forward(query, from_grapheme_index) {
+                return Some(Location {
+                    grapheme_index,
+                    line_index,
+                });
+            }
+        }
+        None
+    }
+
+    /// Finds the previous match of `query` before `from`, wrapping around at the start of the buffer.
+    pub fn search_backward(&self, query: &SearchQuery, from: Location) -> Option<Location> {
+        let mut is_first = true;
+        for (line_index, line) in self
+            .lines
+            .iter()
+            .enumerate()
+            .rev()
+            .cycle()
+            .skip(
+                self.lines
+                    .len()
+                    .saturating_sub(from.line_index)
+                    .saturating_sub(1),
+            )
+            // The starting line is visited twice: once up to `from` and once from its end.
+            .take(self.lines.len().saturating_add(1))
+        {
+            let before_grapheme_index = if is_first {
+                is_first = false;
+                from.grapheme_index
+            } else {
+                line.grapheme_count()
+            };
+            if let Some(grapheme_index) = line.search_backward(query, before_grapheme_index) {
+                return Some(Location {
+                    grapheme_index,
+                    line_index,
+                });
+            }
+        }
+        None
+    }
+
+    // endregion
+
+    // region: highlighting
+
+    /// Brings the syntax highlighting of all lines up to and including `last_line_index` up to date.
+    /// Only edited lines and the lines below them whose starting state changed are highlighted again.
+    pub fn update_highlights(&mut self, last_line_index: usize) {
+        if let Some(syntax_highlighter) = highlighter::for_file_type(self.file_info.get_file_type())
+        {
+            self.highlights
+                .update(syntax_highlighter, &self.lines, last_line_index);
+        }
+    }
+    pub fn syntax_annotations(&self, line_index: usize) -> &[Annotation] {
+        self.highlights.annotations(line_index)
+    }
+
+    // endregion
+
     pub fn is_empty(&self) -> bool {
         self.lines.is_empty()
     }
+    pub const fn revision(&self) -> usize {
+        self.revision
+    }
     pub const fn is_file_loaded(&self) -> bool {
         self.file_info.has_path()
     }
@@ -65,10 +354,11 @@ impl Buffer {
         }
         if at.line_index == self.height() {
             self.lines.push(Line::from(&character.to_string()));
-            self.dirty = true;
+            self.record_change(at.line_index, Vec::new(), 1);
         } else if let Some(line) = self.lines.get_mut(at.line_index) {
+            let removed = vec![line.to_string()];
             line.insert_char(character, at.grapheme_index);
-            self.dirty = true;
+            self.record_change(at.line_index, removed, 1);
         }
     }
     pub fn delete(&mut self, at: Location) {
@@ -76,27 +366,537 @@ impl Buffer {
             if at.grapheme_index >= line.grapheme_count()
                 && self.height() > at.line_index.saturating_add(1)
             {
+                let mut removed = vec![line.to_string()];
                 let next_line = self.lines.remove(at.line_index.saturating_add(1));
+                removed.push(next_line.to_string());
                 // clippy::indexing_slicing: We checked for existence of this line in the surrounding if statment
                 #[allow(clippy::indexing_slicing)]
                 self.lines[at.line_index].append(&next_line);
-                self.dirty = true;
+                self.record_change(at.line_index, removed, 1);
             } else if at.grapheme_index < line.grapheme_count() {
+                let removed = vec![line.to_string()];
                 // clippy::indexing_slicing: We checked for existence of this line in the surrounding if statment
                 #[allow(clippy::indexing_slicing)]
                 self.lines[at.line_index].delete(at.grapheme_index);
-                self.dirty = true;
+                self.record_change(at.line_index, removed, 1);
             }
         }
     }
     pub fn insert_newline(&mut self, at: Location) {
         if at.line_index == self.height() {
             self.lines.push(Line::default());
-            self.dirty = true;
+            self.record_change(at.line_index, Vec::new(), 1);
         } else if let Some(line) = self.lines.get_mut(at.line_index) {
+            let removed = vec![line.to_string()];
             let new = line.split(at.grapheme_index);
             self.lines.insert(at.line_index.saturating_add(1), new);
-            self.dirty = true;
+            self.record_change(at.line_index, removed, 2);
+        }
+    }
+
+    /// Returns the text between `start` (inclusive) and `end` (exclusive), with lines joined by `\n`.
+    pub fn text_range(&self, start: Location, end: Location) -> String {
+        let mut result = String::new();
+        if start >= end {
+            return result;
+        }
+        for (line_index, line) in self.lines.iter().enumerate().skip(start.line_index).take(
+            end.line_index
+                .saturating_sub(start.line_index)
+                .saturating_add(1),
+        ) {
+            if line_index > start.line_index {
+                result.push('\n');
+            }
+            let from = if line_index == start.line_index {
+                start.grapheme_index
+            } else {
+                0
+            };
+            let to = if line_index == end.line_index {
+                end.grapheme_index
+            } else {
+                line.grapheme_count()
+            };
+            result.push_str(&line.substr(from..to));
+        }
+        // The end may lie on the line after the last one, which starts after a final newline.
+        if end.line_index >= self.height() && end.line_index > start.line_index {
+            result.push('\n');
+        }
+        result
+    }
+
+    /// Inserts `text`, which may contain newlines, at `at` as a single change
+    /// and returns the location right after the inserted text.
+    pub fn insert_text(&mut self, at: Location, text: &str) -> Location {
+        if at.line_index > self.height() || text.is_empty() {
+            return at;
+        }
+        let (prefix, suffix, removed) = match self.lines.get(at.line_index) {
+            Some(line) => (
+                line.substr(0..at.grapheme_index),
+                line.substr(at.grapheme_index..line.grapheme_count()),
+                vec![line.to_string()],
+            ),
+            None => (String::new(), String::new(), Vec::new()),
+        };
+        let combined = format!("{prefix}{text}{suffix}");
+        let mut new_lines: Vec<Line> = combined.split('\n').map(Line::from).collect();
+        // Text ending in a newline that is inserted behind the last line must not leave an extra empty line.
+        if removed.is_empty() && new_lines.len() > 1 && combined.ends_with('\n') {
+            new_lines.pop();
+        }
+        let inserted_count = new_lines.len();
+        let end = if removed.is_empty() && combined.ends_with('\n') {
+            Location {
+                line_index: at.line_index.saturating_add(inserted_count),
+                grapheme_index: 0,
+            }
+        } else {
+            let last_len = new_lines.last().map_or(0, Line::grapheme_count);
+            Location {
+                line_index: at
+                    .line_index
+                    .saturating_add(inserted_count.saturating_sub(1)),
+                grapheme_index: last_len.saturating_sub(Line::from(&suffix).grapheme_count()),
+            }
+        };
+        let replaced_count = removed.len();
+        self.lines.splice(
+            at.line_index..at.line_index.saturating_add(replaced_count),
+            new_lines,
+        );
+        self.record_change(at.line_index, removed, inserted_count);
+        end
+    }
+
+    /// Deletes the text between `start` (inclusive) and `end` (exclusive), which may span multiple lines.
+    pub fn delete_range(&mut self, start: Location, end: Location) {
+        if start >= end || start.line_index >= self.height() {
+            return;
+        }
+        let last_index = end.line_index.min(self.height().saturating_sub(1));
+        let (Some(first), Some(last)) =
+            (self.lines.get(start.line_index), self.lines.get(last_index))
+        else {
+            return;
+        };
+        // The end may lie behind the last line, in which case everything up to the end of the buffer is deleted.
+        let end_grapheme_index = if last_index == end.line_index {
+            end.grapheme_index
+        } else {
+            last.grapheme_count()
+        };
+        let mut merged = first.substr(0..start.grapheme_index);
+        merged.push_str(&last.substr(end_grapheme_index..last.grapheme_count()));
+        // clippy::indexing_slicing: We checked for existence of both lines above
+        #[allow(clippy::indexing_slicing)]
+        let removed = self.lines[start.line_index..=last_index]
+            .iter()
+            .map(Line::to_string)
+            .collect();
+        // Deleting whole lines up to the end of the buffer leaves nothing behind, not even an empty line.
+        let replacement = if last_index < end.line_index && start.grapheme_index == 0 {
+            Vec::new()
+        } else {
+            vec![Line::from(&merged)]
+        };
+        let inserted_count = replacement.len();
+        self.lines
+            .splice(start.line_index..=last_index, replacement);
+        self.record_change(start.line_index, removed, inserted_count);
+    }
+
+    /// Replaces the match of `query` at `at` and returns the number of graphemes inserted in its place.
+    pub fn replace_match(
+        &mut self,
+        at: Location,
+        query: &SearchQuery,
+        replacement: &str,
+    ) -> Option<usize> {
+        let line = self.lines.get_mut(at.line_index)?;
+        let removed = vec![line.to_string()];
+        let replacement_len = line.replace_match(at.grapheme_index, query, replacement)?;
+        self.record_change(at.line_index, removed, 1);
+        Some(replacement_len)
+    }
+
+    // region: history
+
+    // Records that the lines in `removed`, starting at `line_index`, have been replaced
+    // by the `inserted_count` lines now found there.
+    fn record_change(&mut self, line_index: usize, removed: Vec<String>, inserted_count: usize) {
+        let inserted = self
+            .lines
+            .iter()
+            .skip(line_index)
+            .take(inserted_count)
+            .map(Line::to_string)
+            .collect();
+        self.highlights
+            .invalidate(line_index, removed.len(), inserted_count);
+        self.swap_file.mark_stale();
+        self.revision = self.revision.wrapping_add(1);
+        self.history.record(Change {
+            line_index,
+            removed,
+            inserted,
+        });
+        self.dirty = true;
+    }
+    /// Turns the changes made by the current edit command into an undo step.
+    pub fn commit_changes(
+        &mut self,
+        caret_before: Location,
+        caret_after: Location,
+        coalesce: bool,
+    ) {
+        self.history.commit(caret_before, caret_after, coalesce);
+    }
+    /// Ensures the next edit starts a new undo step.
+    pub fn seal_history(&mut self) {
+        self.history.seal();
+    }
+    /// Reverts the most recent undo step and returns the caret location from before that step.
+    pub fn undo(&mut self) -> Option<Location> {
+        let step = self.history.undo()?;
+        for change in step.changes.iter().rev() {
+            Self::replace_lines(
+                &mut self.lines,
+                change.line_index,
+                change.inserted.len(),
+                &change.removed,
+            );
+            self.highlights.invalidate(
+                change.line_index,
+                change.inserted.len(),
+                change.removed.len(),
+            );
+        }
+        let caret = step.caret_before;
+        self.swap_file.mark_stale();
+        self.revision = self.revision.wrapping_add(1);
+        self.update_dirty();
+        Some(caret)
+    }
+    /// Re-applies the most recently undone step and returns the caret location after that step.
+    pub fn redo(&mut self) -> Option<Location> {
+        let step = self.history.redo()?;
+        for change in &step.changes {
+            Self::replace_lines(
+                &mut self.lines,
+                change.line_index,
+                change.removed.len(),
+                &change.inserted,
+            );
+            self.highlights.invalidate(
+                change.line_index,
+                change.removed.len(),
+                change.inserted.len(),
+            );
+        }
+        let caret = step.caret_after;
+        self.swap_file.mark_stale();
+        self.revision = self.revision.wrapping_add(1);
+        self.update_dirty();
+        Some(caret)
+    }
+    fn replace_lines(lines: &mut Vec<Line>, at: usize, count: usize, replacement: &[String]) {
+        let end = at.saturating_add(count).min(lines.len());
+        let start = at.min(end);
+        lines.splice(start..end, replacement.iter().map(|line| Line::from(line)));
+    }
+
+    // endregion
+}
+
+// region: atomic writes
+
+/// Replaces the contents of the file at `path` with `bytes` without ever leaving it half-written:
+/// the bytes go to a temporary file next to it first, which then replaces the original in a
+/// single rename. Symlinks are followed, so the file they point to is replaced rather than the
+/// link, and the original's permissions and, where allowed, ownership carry over.
+/// Read-only files are refused, and files with several hard links are overwritten in place,
+/// as a rename would leave the other links with the old contents.
+fn write_atomically(path: &Path, bytes: &[u8]) -> Result<(), Error> {
+    // A path that doesn't exist yet can't be resolved, so a new file is created as given.
+    let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if let Ok(metadata) = fs::metadata(&target) {
+        if metadata.permissions().readonly() {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                format!("{} is read-only", target.display()),
+            ));
+        }
+        if has_other_links(&metadata) {
+            return write_in_place(&target, bytes);
         }
     }
+    let temp_path = temp_path_for(&target)?;
+    let result = write_temp_file(&temp_path, &target, bytes).and_then(|()| {
+        fs::rename(&temp_path, &target)
+            .map_err(|err| with_context(&err, &format!("could not replace {}", target.display())))
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+        return result;
+    }
+    // Make the rename itself durable. Not every platform can open directories, so this is best effort.
+    if let Some(dir) = target.parent() {
+        let _ = File::open(dir).and_then(|dir| dir.sync_all());
+    }
+    Ok(())
+}
+
+fn temp_path_for(target: &Path) -> Result<PathBuf, Error> {
+    let file_name = target
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} is not a file name", target.display()),
+            )
+        })?;
+    let temp_name = format!(".{file_name}.{}.tmp", process::id());
+    Ok(target.with_file_name(temp_name))
+}
+
+fn write_temp_file(temp_path: &Path, target: &Path, bytes: &[u8]) -> Result<(), Error> {
+    let metadata = fs::metadata(target).ok();
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    // The temporary file starts out with the original's permissions, so that the copy of a
+    // private file is never readable by others, not even before it's complete.
+    if let Some(metadata) = &metadata {
+        copy_mode(&mut options, metadata);
+    }
+    let mut file = options.open(temp_path).map_err(|err| {
+        let dir = temp_path.parent().unwrap_or(temp_path);
+        with_context(
+            &err,
+            &format!("could not create a temporary file in {}", dir.display()),
+        )
+    })?;
+    if let Some(metadata) = &metadata {
+        // The umask may have taken away some of the permissions when the file was created.
+        fs::set_permissions(temp_path, metadata.permissions()).map_err(|err| {
+            with_context(
+                &err,
+                &format!("could not copy the permissions of {}", target.display()),
+            )
+        })?;
+        copy_ownership(temp_path, metadata);
+    }
+    file.write_all(bytes)
+        .and_then(|()| file.sync_all())
+        .map_err(|err| with_context(&err, &format!("could not write {}", temp_path.display())))
+}
+
+fn write_in_place(target: &Path, bytes: &[u8]) -> Result<(), Error> {
+    OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .open(target)
+        .and_then(|mut file| {
+            file.write_all(bytes)?;
+            file.sync_all()
+        })
+        .map_err(|err| with_context(&err, &format!("could not write {}", target.display())))
+}
+
+#[cfg(unix)]
+fn has_other_links(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink() > 1
+}
+
+#[cfg(not(unix))]
+const fn has_other_links(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn copy_mode(options: &mut OpenOptions, metadata: &fs::Metadata) {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+    options.mode(metadata.permissions().mode());
+}
+
+#[cfg(not(unix))]
+fn copy_mode(_options: &mut OpenOptions, _metadata: &fs::Metadata) {}
+
+// Only the owner can give a file away, so failing to do so is not an error.
+#[cfg(unix)]
+fn copy_ownership(temp_path: &Path, metadata: &fs::Metadata) {
+    use std::os::unix::fs::{chown, MetadataExt};
+    let _ = chown(temp_path, Some(metadata.uid()), Some(metadata.gid()));
+}
+
+#[cfg(not(unix))]
+fn copy_ownership(_temp_path: &Path, _metadata: &fs::Metadata) {}
+
+fn with_context(err: &Error, context: &str) -> Error {
+    Error::new(err.kind(), format!("{context}: {err}"))
+}
+
+// endregion
+
+#[cfg(test)]
+mod tests {
+    use super::{temp_path_for, write_atomically, Buffer, Encoding, LineEnding, Location};
+    use std::{fs, io::ErrorKind};
+    use tempfile::tempdir;
+
+    #[test]
+    fn creates_a_new_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("new.txt");
+        write_atomically(&path, b"hello\n").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"hello\n");
+        // Only the file itself is left behind.
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn replaces_the_target_of_a_symlink() {
+        let dir = tempdir().unwrap();
+        let target = dir.path().join("target.txt");
+        let link = dir.path().join("link.txt");
+        fs::write(&target, "old").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        write_atomically(&link, b"new").unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+        assert_eq!(fs::read(&target).unwrap(), b"new");
+    }
+
+    #[test]
+    fn writes_hard_linked_files_in_place() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        let other = dir.path().join("other.txt");
+        fs::write(&path, "old").unwrap();
+        fs::hard_link(&path, &other).unwrap();
+        write_atomically(&path, b"new").unwrap();
+        assert_eq!(fs::read(&other).unwrap(), b"new");
+    }
+
+    #[test]
+    fn refuses_read_only_files() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        fs::write(&path, "old").unwrap();
+        let mut permissions = fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&path, permissions).unwrap();
+        let err = write_atomically(&path, b"new").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        assert_eq!(fs::read(&path).unwrap(), b"old");
+    }
+
+    #[test]
+    fn failed_write_leaves_the_original_untouched() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        fs::write(&path, "old").unwrap();
+        // Something in the way of the temporary file makes creating it fail.
+        let blocker = temp_path_for(&path).unwrap();
+        fs::create_dir(&blocker).unwrap();
+        assert!(write_atomically(&path, b"new").is_err());
+        assert_eq!(fs::read(&path).unwrap(), b"old");
+        assert!(blocker.is_dir());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_the_permissions_of_private_files() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("private.txt");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        write_atomically(&path, b"new").unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+    }
+
+    #[test]
+    fn saving_text_that_latin1_cant_hold_fails_until_the_encoding_changes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("latin1.txt");
+        fs::write(&path, b"caf\xE9\n").unwrap();
+        let mut buffer = Buffer::load(path.to_str().unwrap()).unwrap();
+        assert_eq!(buffer.encoding, Encoding::Latin1);
+        assert!(buffer.fits_encoding());
+
+        buffer.insert_char('世', Location::default());
+        assert!(!buffer.fits_encoding());
+        let err = buffer.save().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(fs::read(&path).unwrap(), b"caf\xE9\n");
+
+        buffer.set_encoding(Encoding::Utf8);
+        buffer.save().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "世café\n");
+    }
+
+    // Loads `bytes` from a file, saves it without editing and returns the buffer and the bytes on disk.
+    fn load_and_save(bytes: &[u8]) -> (Buffer, Vec<u8>) {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        fs::write(&path, bytes).unwrap();
+        let mut buffer = Buffer::load(path.to_str().unwrap()).unwrap();
+        buffer.save().unwrap();
+        (buffer, fs::read(&path).unwrap())
+    }
+
+    #[test]
+    fn saving_without_editing_keeps_the_bytes() {
+        for bytes in [
+            &b"a\nb\n"[..],
+            b"a\r\nb\r\n",
+            b"a\r\nb\nc\r\n",
+            b"a\nb\r\nc",
+            b"a\r\nb",
+            b"stray\r\r\nreturn\rhere\r\n",
+            b"\n\n",
+            b"",
+        ] {
+            assert_eq!(load_and_save(bytes).1, bytes);
+        }
+    }
+
+    #[test]
+    fn mixed_line_endings_keep_their_carriage_returns_in_the_text() {
+        let (buffer, _) = load_and_save(b"a\r\nb\nc\r\n");
+        assert_eq!(buffer.line_ending, LineEnding::Lf);
+        let lines: Vec<String> = buffer.lines.iter().map(ToString::to_string).collect();
+        assert_eq!(lines, ["a\r", "b", "c\r"]);
+    }
+
+    #[test]
+    fn a_missing_final_newline_is_kept() {
+        let (buffer, bytes) = load_and_save(b"a\r\nb");
+        assert!(buffer.missing_final_newline);
+        assert_eq!(buffer.line_ending, LineEnding::CrLf);
+        assert_eq!(buffer.height(), 2);
+        assert_eq!(bytes, b"a\r\nb");
+        assert!(!load_and_save(b"a\n").0.missing_final_newline);
+    }
+
+    #[test]
+    fn converting_mixed_line_endings_makes_them_uniform() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        fs::write(&path, b"a\r\nb\nc\r\n").unwrap();
+        let mut buffer = Buffer::load(path.to_str().unwrap()).unwrap();
+        buffer.set_line_ending(LineEnding::CrLf);
+        buffer.save().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"a\r\nb\r\nc\r\n");
+        buffer.set_line_ending(LineEnding::Lf);
+        buffer.save().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"a\nb\nc\n");
+    }
 }
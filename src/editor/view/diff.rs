@@ -0,0 +1,240 @@
+// Comparing stretches of changed lines takes time and memory proportional to the product of
+// their lengths, so longer ones are shown as replaced wholesale instead.
+const MAX_COMPARED_CELLS: usize = 4_000_000;
+// How many unchanged lines are shown around each change.
+const CONTEXT_LINES: usize = 2;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DiffKind {
+    Unchanged,
+    // Only in the buffer.
+    Removed,
+    // Only in the file on disk.
+    Added,
+    // Stands for unchanged lines that are left out.
+    Skipped,
+}
+
+/// A line of a diff, with its index in the buffer, or in the file on disk for added lines.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DiffLine {
+    pub kind: DiffKind,
+    pub line_index: usize,
+    pub text: String,
+}
+
+#[derive(Copy, Clone)]
+enum Step {
+    Same(usize),
+    Ours(usize),
+    Theirs(usize),
+}
+
+/// Compares the lines of the buffer with those on disk and returns the changes between them
+/// with some unchanged lines around each, or nothing if both are the same.
+pub fn diff_lines(ours: &[String], theirs: &[String]) -> Vec<DiffLine> {
+    let steps = steps(ours, theirs);
+    let mut is_shown = vec![false; steps.len()];
+    for (index, step) in steps.iter().enumerate() {
+        if !matches!(step, Step::Same(_)) {
+            let end = index.saturating_add(CONTEXT_LINES).saturating_add(1);
+            for is_shown in is_shown
+                .iter_mut()
+                .take(end)
+                .skip(index.saturating_sub(CONTEXT_LINES))
+            {
+                *is_shown = true;
+            }
+        }
+    }
+    if !steps.iter().any(|step| !matches!(step, Step::Same(_))) {
+        return Vec::new();
+    }
+    let line = |kind: DiffKind, lines: &[String], line_index: usize| DiffLine {
+        kind,
+        line_index,
+        text: lines.get(line_index).cloned().unwrap_or_default(),
+    };
+    let mut diff = Vec::new();
+    let mut was_shown = true;
+    for (step, &is_shown) in steps.iter().zip(&is_shown) {
+        if !is_shown {
+            if was_shown {
+                diff.push(DiffLine {
+                    kind: DiffKind::Skipped,
+                    line_index: 0,
+                    text: String::new(),
+                });
+            }
+            was_shown = false;
+            continue;
+        }
+        was_shown = true;
+        diff.push(match *step {
+            Step::Same(index) => line(DiffKind::Unchanged, ours, index),
+            Step::Ours(index) => line(DiffKind::Removed, ours, index),
+            Step::Theirs(index) => line(DiffKind::Added, theirs, index),
+        });
+    }
+    diff
+}
+
+// Turns `ours` into `theirs` step by step, keeping as many lines as possible.
+fn steps(ours: &[String], theirs: &[String]) -> Vec<Step> {
+    let prefix = ours
+        .iter()
+        .zip(theirs)
+        .take_while(|(ours, theirs)| ours == theirs)
+        .count();
+    let suffix = ours
+        .iter()
+        .rev()
+        .zip(theirs.iter().rev())
+        .take(ours.len().min(theirs.len()).saturating_sub(prefix))
+        .take_while(|(ours, theirs)| ours == theirs)
+        .count();
+    let ours_end = ours.len().saturating_sub(suffix);
+    let theirs_end = theirs.len().saturating_sub(suffix);
+
+    let mut steps: Vec<Step> = (0..prefix).map(Step::Same).collect();
+    compare(
+        ours.get(prefix..ours_end).unwrap_or_default(),
+        theirs.get(prefix..theirs_end).unwrap_or_default(),
+        prefix,
+        &mut steps,
+    );
+    steps.extend((ours_end..ours.len()).map(Step::Same));
+    steps
+}
+
+// Compares two stretches of lines that both start at `offset`, using their longest common
+// subsequence.
+fn compare(ours: &[String], theirs: &[String], offset: usize, steps: &mut Vec<Step>) {
+    let (ours_len, theirs_len) = (ours.len(), theirs.len());
+    if ours_len.saturating_mul(theirs_len) > MAX_COMPARED_CELLS {
+        steps.extend((0..ours_len).map(|index| Step::Ours(offset.saturating_add(index))));
+        steps.extend((0..theirs_len).map(|index| Step::Theirs(offset.saturating_add(index))));
+        return;
+    }
+    // The length of the longest common subsequence of ours[i..] and theirs[j..].
+    let width = theirs_len.saturating_add(1);
+    let cell = |i: usize, j: usize| i.saturating_mul(width).saturating_add(j);
+    let mut lengths = vec![0_usize; ours_len.saturating_add(1).saturating_mul(width)];
+    for (i, our_line) in ours.iter().enumerate().rev() {
+        for (j, their_line) in theirs.iter().enumerate().rev() {
+            let length = |i: usize, j: usize| lengths.get(cell(i, j)).copied().unwrap_or_default();
+            let value = if our_line == their_line {
+                length(i.saturating_add(1), j.saturating_add(1)).saturating_add(1)
+            } else {
+                length(i.saturating_add(1), j).max(length(i, j.saturating_add(1)))
+            };
+            if let Some(target) = lengths.get_mut(cell(i, j)) {
+                *target = value;
+            }
+        }
+    }
+    let length = |i: usize, j: usize| lengths.get(cell(i, j)).copied().unwrap_or_default();
+    let (mut i, mut j) = (0, 0);
+    while i < ours_len && j < theirs_len {
+        if ours.get(i) == theirs.get(j) {
+            steps.push(Step::Same(offset.saturating_add(i)));
+            i = i.saturating_add(1);
+            j = j.saturating_add(1);
+        } else if length(i.saturating_add(1), j) >= length(i, j.saturating_add(1)) {
+            steps.push(Step::Ours(offset.saturating_add(i)));
+            i = i.saturating_add(1);
+        } else {
+            steps.push(Step::Theirs(offset.saturating_add(j)));
+            j = j.saturating_add(1);
+        }
+    }
+    steps.extend((i..ours_len).map(|index| Step::Ours(offset.saturating_add(index))));
+    steps.extend((j..theirs_len).map(|index| Step::Theirs(offset.saturating_add(index))));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff_lines, DiffKind, DiffLine};
+
+    fn lines(text: &str) -> Vec<String> {
+        text.split(' ').map(String::from).collect()
+    }
+
+    fn line(kind: DiffKind, line_index: usize, text: &str) -> DiffLine {
+        DiffLine {
+            kind,
+            line_index,
+            text: text.to_string(),
+        }
+    }
+
+    fn skipped() -> DiffLine {
+        line(DiffKind::Skipped, 0, "")
+    }
+
+    #[test]
+    fn equal_lines_have_no_diff() {
+        assert!(diff_lines(&lines("a b c"), &lines("a b c")).is_empty());
+        assert!(diff_lines(&[], &[]).is_empty());
+    }
+
+    #[test]
+    fn shows_an_insertion_with_context() {
+        let diff = diff_lines(&lines("1 2 3 4 5 6 7 8 9"), &lines("1 2 3 4 5 x 6 7 8 9"));
+        assert_eq!(
+            diff,
+            vec![
+                skipped(),
+                line(DiffKind::Unchanged, 3, "4"),
+                line(DiffKind::Unchanged, 4, "5"),
+                line(DiffKind::Added, 5, "x"),
+                line(DiffKind::Unchanged, 5, "6"),
+                line(DiffKind::Unchanged, 6, "7"),
+                skipped(),
+            ]
+        );
+    }
+
+    #[test]
+    fn shows_a_deletion_with_context() {
+        let diff = diff_lines(&lines("x 1 2 3 4"), &lines("1 2 3 4"));
+        assert_eq!(
+            diff,
+            vec![
+                line(DiffKind::Removed, 0, "x"),
+                line(DiffKind::Unchanged, 1, "1"),
+                line(DiffKind::Unchanged, 2, "2"),
+                skipped(),
+            ]
+        );
+    }
+
+    #[test]
+    fn shows_a_changed_line_as_removed_then_added() {
+        let diff = diff_lines(&lines("a b c"), &lines("a B c"));
+        assert_eq!(
+            diff,
+            vec![
+                line(DiffKind::Unchanged, 0, "a"),
+                line(DiffKind::Removed, 1, "b"),
+                line(DiffKind::Added, 1, "B"),
+                line(DiffKind::Unchanged, 2, "c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_lines_common_to_both_between_changes() {
+        let diff = diff_lines(&lines("a b c d"), &lines("b c d e"));
+        assert_eq!(
+            diff,
+            vec![
+                line(DiffKind::Removed, 0, "a"),
+                line(DiffKind::Unchanged, 1, "b"),
+                line(DiffKind::Unchanged, 2, "c"),
+                line(DiffKind::Unchanged, 3, "d"),
+                line(DiffKind::Added, 3, "e"),
+            ]
+        );
+    }
+}
@@ -3,15 +3,23 @@ use std::{
     path::{Path, PathBuf},
 };
 
+use super::{FileStamp, FileType};
+
 #[derive(Default, Debug)]
 pub struct FileInfo {
     path: Option<PathBuf>,
+    file_type: FileType,
+    // The state of the file on disk as of the last load or save.
+    stamp: Option<FileStamp>,
 }
 
 impl FileInfo {
     pub fn from(file_name: &str) -> Self {
+        let path = PathBuf::from(file_name);
         Self {
-            path: Some(PathBuf::from(file_name)),
+            file_type: FileType::from_path(&path),
+            path: Some(path),
+            stamp: None,
         }
     }
     pub fn get_path(&self) -> Option<&Path> {
@@ -20,6 +28,19 @@ impl FileInfo {
     pub const fn has_path(&self) -> bool {
         self.path.is_some()
     }
+    pub const fn get_file_type(&self) -> FileType {
+        self.file_type
+    }
+    pub fn set_stamp(&mut self, stamp: Option<FileStamp>) {
+        self.stamp = stamp;
+    }
+    /// Checks whether the file on disk was changed since it was last loaded or saved.
+    pub fn is_changed_on_disk(&self) -> bool {
+        match (self.get_path(), &self.stamp) {
+            (Some(path), Some(stamp)) => stamp.is_outdated(path),
+            _ => false,
+        }
+    }
 }
 
 impl Display for FileInfo {
@@ -0,0 +1,126 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::Path,
+    time::SystemTime,
+};
+
+/// What a file looked like on disk when it was last loaded or saved,
+/// used to tell whether another program changed it since.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct FileStamp {
+    modified: Option<SystemTime>,
+    size: u64,
+    hash: u64,
+}
+
+impl FileStamp {
+    /// Stamps the file at `path`, whose contents are `bytes`.
+    pub fn new(path: &Path, bytes: &[u8]) -> Self {
+        let metadata = fs::metadata(path).ok();
+        Self {
+            modified: metadata
+                .as_ref()
+                .and_then(|metadata| metadata.modified().ok()),
+            size: metadata.map_or(0, |metadata| metadata.len()),
+            hash: Self::hash(bytes),
+        }
+    }
+
+    /// Checks whether the file at `path` no longer matches the stamp. The file is only read if
+    /// its modification time or size changed, and a file that was merely touched still matches.
+    /// A file that is gone doesn't count as changed, since saving simply creates it again.
+    pub fn is_outdated(&self, path: &Path) -> bool {
+        let Ok(metadata) = fs::metadata(path) else {
+            return false;
+        };
+        if metadata.modified().ok() == self.modified && metadata.len() == self.size {
+            return false;
+        }
+        fs::read(path).map_or(true, |bytes| Self::hash(&bytes) != self.hash)
+    }
+
+    fn hash(bytes: &[u8]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FileStamp;
+    use std::{
+        fs::{self, File},
+        path::Path,
+        time::{Duration, SystemTime},
+    };
+    use tempfile::tempdir;
+
+    fn set_modified(path: &Path, time: SystemTime) {
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(time)
+            .unwrap();
+    }
+
+    #[test]
+    fn an_untouched_file_matches() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        fs::write(&path, "hello").unwrap();
+        let stamp = FileStamp::new(&path, b"hello");
+        assert!(!stamp.is_outdated(&path));
+    }
+
+    #[test]
+    fn a_touched_file_with_the_same_contents_matches() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        fs::write(&path, "hello").unwrap();
+        let stamp = FileStamp::new(&path, b"hello");
+        set_modified(&path, SystemTime::now() + Duration::from_mins(1));
+        assert!(!stamp.is_outdated(&path));
+    }
+
+    #[test]
+    fn a_change_in_size_is_noticed() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        fs::write(&path, "hello").unwrap();
+        let stamp = FileStamp::new(&path, b"hello");
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+        fs::write(&path, "hello, world").unwrap();
+        // Even with the old modification time, the size gives the change away.
+        set_modified(&path, modified);
+        assert!(stamp.is_outdated(&path));
+    }
+
+    #[test]
+    fn a_change_of_the_same_size_is_noticed_by_its_contents() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        fs::write(&path, "hello").unwrap();
+        let stamp = FileStamp::new(&path, b"hello");
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+        fs::write(&path, "jello").unwrap();
+        set_modified(&path, modified + Duration::from_mins(1));
+        assert!(stamp.is_outdated(&path));
+        // With neither the modification time nor the size changed, the file isn't read at all.
+        set_modified(&path, modified);
+        assert!(!stamp.is_outdated(&path));
+    }
+
+    #[test]
+    fn a_deleted_file_is_not_outdated() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        fs::write(&path, "hello").unwrap();
+        let stamp = FileStamp::new(&path, b"hello");
+        fs::remove_file(&path).unwrap();
+        assert!(!stamp.is_outdated(&path));
+    }
+}
@@ -0,0 +1,404 @@
+use std::ops::Range;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{Annotation, AnnotationType, FileType, Line};
+mod markdown;
+mod syntax;
+
+/// The construct a line ends in, which the next line continues.
+#[derive(Copy, Clone, Default, Eq, PartialEq, Debug)]
+pub enum HighlightState {
+    #[default]
+    Normal,
+    // A (possibly nested) block comment with the given depth.
+    BlockComment(usize),
+    // A string closed by the given delimiter.
+    String(&'static str),
+    // A raw string closed by `"` and the given number of `#`.
+    RawString(usize),
+    // A fenced code block closed by the given fence.
+    CodeBlock(&'static str),
+}
+
+impl HighlightState {
+    /// Returns how the content of the construct is annotated, or `None` for normal text.
+    const fn annotation_type(self) -> Option<AnnotationType> {
+        match self {
+            Self::Normal => None,
+            Self::BlockComment(_) => Some(AnnotationType::Comment),
+            Self::String(_) | Self::RawString(_) | Self::CodeBlock(_) => {
+                Some(AnnotationType::String)
+            }
+        }
+    }
+}
+
+/// Annotates a single line, given as its graphemes, with syntax annotations.
+/// Highlighting starts in `state`, the state the previous line ended in,
+/// and returns the state the line itself ends in alongside the annotations.
+pub trait SyntaxHighlighter {
+    fn highlight(
+        &self,
+        graphemes: &[&str],
+        state: HighlightState,
+    ) -> (Vec<Annotation>, HighlightState);
+}
+
+/// Returns the highlighter for the given file type, or `None` for plain text.
+pub fn for_file_type(file_type: FileType) -> Option<&'static dyn SyntaxHighlighter> {
+    match file_type {
+        FileType::Rust => Some(&syntax::RUST),
+        FileType::Toml => Some(&syntax::TOML),
+        FileType::Json => Some(&syntax::JSON),
+        FileType::Shell => Some(&syntax::SHELL),
+        FileType::Markdown => Some(&markdown::Markdown),
+        FileType::Python | FileType::Yaml | FileType::Text => None,
+    }
+}
+
+struct HighlightedLine {
+    start_state: HighlightState,
+    end_state: HighlightState,
+    annotations: Vec<Annotation>,
+}
+
+/// Caches the highlighting of each line of a buffer together with the state it started and
+/// ended in. Edited lines are invalidated, and when lines are highlighted again, the lines
+/// below them are only re-highlighted until they start in the same state as before.
+#[derive(Default)]
+pub struct Highlights {
+    lines: Vec<Option<HighlightedLine>>,
+    // All lines above this one are up to date.
+    first_stale: usize,
+}
+
+impl Highlights {
+    pub fn new(line_count: usize) -> Self {
+        let mut lines = Vec::new();
+        lines.resize_with(line_count, || None);
+        Self {
+            lines,
+            first_stale: 0,
+        }
+    }
+
+    /// Drops the highlighting of the `removed_count` lines starting at `line_index`, which have
+    /// been replaced by `inserted_count` new lines.
+    pub fn invalidate(&mut self, line_index: usize, removed_count: usize, inserted_count: usize) {
+        let start = line_index.min(self.lines.len());
+        let end = line_index
+            .saturating_add(removed_count)
+            .min(self.lines.len());
+        self.lines
+            .splice(start..end, (0..inserted_count).map(|_| None));
+        self.first_stale = self.first_stale.min(start);
+    }
+
+    /// Drops the highlighting of all lines, for instance because the file type changed.
+    pub fn clear(&mut self) {
+        *self = Self::new(self.lines.len());
+    }
+
+    /// Brings the highlighting of all lines up to and including `last_line_index` up to date.
+    pub fn update(
+        &mut self,
+        syntax_highlighter: &dyn SyntaxHighlighter,
+        lines: &[Line],
+        last_line_index: usize,
+    ) {
+        self.lines.resize_with(lines.len(), || None);
+        let end = last_line_index.saturating_add(1).min(lines.len());
+        if self.first_stale >= end {
+            return;
+        }
+        let mut state = self
+            .first_stale
+            .checked_sub(1)
+            .and_then(|previous| self.lines.get(previous))
+            .and_then(Option::as_ref)
+            .map_or_else(HighlightState::default, |previous| previous.end_state);
+        for (highlighted, line) in self
+            .lines
+            .iter_mut()
+            .zip(lines)
+            .take(end)
+            .skip(self.first_stale)
+        {
+            match highlighted {
+                Some(highlighted) if highlighted.start_state == state => {}
+                _ => {
+                    let (annotations, end_state) =
+                        syntax_highlighter.highlight(&line.graphemes(), state);
+                    *highlighted = Some(HighlightedLine {
+                        start_state: state,
+                        end_state,
+                        annotations,
+                    });
+                }
+            }
+            state = highlighted
+                .as_ref()
+                .map_or_else(HighlightState::default, |highlighted| highlighted.end_state);
+        }
+        self.first_stale = end;
+    }
+
+    /// Returns the syntax annotations of the given line, as of the last update.
+    pub fn annotations(&self, line_index: usize) -> &[Annotation] {
+        self.lines
+            .get(line_index)
+            .and_then(Option::as_ref)
+            .map_or(&[], |highlighted| &highlighted.annotations)
+    }
+}
+
+// region: helpers shared by all highlighters
+
+const fn annotate(annotation_type: AnnotationType, range: Range<usize>) -> Annotation {
+    Annotation {
+        annotation_type,
+        range,
+    }
+}
+
+// Checks whether the graphemes starting at `at` spell out `pattern`.
+fn starts_with(graphemes: &[&str], at: usize, pattern: &str) -> bool {
+    let mut index = at;
+    for expected in pattern.graphemes(true) {
+        if graphemes.get(index) != Some(&expected) {
+            return false;
+        }
+        index = index.saturating_add(1);
+    }
+    true
+}
+
+fn is_whitespace(grapheme: &str) -> bool {
+    grapheme.chars().all(char::is_whitespace)
+}
+
+fn is_digit(grapheme: &str) -> bool {
+    grapheme
+        .chars()
+        .next()
+        .is_some_and(|ch| ch.is_ascii_digit())
+}
+
+fn is_word_start(grapheme: &str) -> bool {
+    grapheme
+        .chars()
+        .next()
+        .is_some_and(|ch| ch.is_alphabetic() || ch == '_')
+}
+
+fn is_word_part(grapheme: &str) -> bool {
+    grapheme
+        .chars()
+        .next()
+        .is_some_and(|ch| ch.is_alphanumeric() || ch == '_')
+}
+
+// Returns the index after the last grapheme for which `predicate` holds, starting at `from`.
+fn end_of(graphemes: &[&str], from: usize, predicate: impl Fn(&str) -> bool) -> usize {
+    graphemes
+        .iter()
+        .skip(from)
+        .position(|grapheme| !predicate(grapheme))
+        .map_or(graphemes.len(), |offset| from.saturating_add(offset))
+}
+
+// Returns the index after the closing `delimiter`, skipping escaped characters.
+// Unterminated strings run until the end of the line.
+fn end_of_string(graphemes: &[&str], from: usize, delimiter: &str) -> usize {
+    let mut index = from;
+    while let Some(&grapheme) = graphemes.get(index) {
+        if grapheme == "\\" {
+            index = index.saturating_add(2);
+            continue;
+        }
+        index = index.saturating_add(1);
+        if grapheme == delimiter {
+            return index;
+        }
+    }
+    graphemes.len()
+}
+
+// Returns the number of graphemes in `pattern`.
+fn grapheme_count(pattern: &str) -> usize {
+    pattern.graphemes(true).count()
+}
+
+// endregion
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::{syntax, Annotation, HighlightState, Highlights, Line, SyntaxHighlighter};
+
+    // Highlights Rust and remembers which lines it was asked to highlight.
+    #[derive(Default)]
+    struct Recorder {
+        highlighted: RefCell<Vec<String>>,
+    }
+
+    impl Recorder {
+        fn take(&self) -> Vec<String> {
+            self.highlighted.take()
+        }
+    }
+
+    impl SyntaxHighlighter for Recorder {
+        fn highlight(
+            &self,
+            graphemes: &[&str],
+            state: HighlightState,
+        ) -> (Vec<Annotation>, HighlightState) {
+            self.highlighted.borrow_mut().push(graphemes.concat());
+            syntax::RUST.highlight(graphemes, state)
+        }
+    }
+
+    fn lines(texts: &[&str]) -> Vec<Line> {
+        texts.iter().map(|text| Line::from(text)).collect()
+    }
+
+    fn start_state(highlights: &Highlights, line_index: usize) -> Option<HighlightState> {
+        highlights
+            .lines
+            .get(line_index)
+            .and_then(Option::as_ref)
+            .map(|highlighted| highlighted.start_state)
+    }
+
+    const TEXT: &[&str] = &[
+        "fn a() {}",
+        "let b = 1;",
+        "let c = 2;",
+        "let d = 3;",
+        "let e = 4;",
+    ];
+
+    #[test]
+    fn highlights_only_up_to_the_requested_line() {
+        let recorder = Recorder::default();
+        let lines = lines(TEXT);
+        let mut highlights = Highlights::new(lines.len());
+        highlights.update(&recorder, &lines, 1);
+        assert_eq!(recorder.take(), ["fn a() {}", "let b = 1;"]);
+        highlights.update(&recorder, &lines, 4);
+        assert_eq!(recorder.take(), ["let c = 2;", "let d = 3;", "let e = 4;"]);
+        highlights.update(&recorder, &lines, 4);
+        assert!(recorder.take().is_empty());
+    }
+
+    #[test]
+    fn an_edit_that_keeps_the_state_rehighlights_only_that_line() {
+        let recorder = Recorder::default();
+        let mut lines = lines(TEXT);
+        let mut highlights = Highlights::new(lines.len());
+        highlights.update(&recorder, &lines, 4);
+        recorder.take();
+
+        lines[1] = Line::from("let b = 10;");
+        highlights.invalidate(1, 1, 1);
+        highlights.update(&recorder, &lines, 4);
+        assert_eq!(recorder.take(), ["let b = 10;"]);
+    }
+
+    #[test]
+    fn opening_a_block_comment_propagates_until_it_is_closed() {
+        let recorder = Recorder::default();
+        let mut lines = lines(&[
+            "let a = 1;",
+            "let b = 2;",
+            "let c = 3;",
+            "*/ let d = 4;",
+            "let e = 5;",
+        ]);
+        let mut highlights = Highlights::new(lines.len());
+        highlights.update(&recorder, &lines, 4);
+        recorder.take();
+
+        lines[1] = Line::from("/* let b = 2;");
+        highlights.invalidate(1, 1, 1);
+        // Only the lines up to the end of the view are highlighted at first.
+        highlights.update(&recorder, &lines, 2);
+        assert_eq!(recorder.take(), ["/* let b = 2;", "let c = 3;"]);
+        // The lines below pick up the comment once they are shown, up to where it closes.
+        highlights.update(&recorder, &lines, 4);
+        assert_eq!(recorder.take(), ["*/ let d = 4;"]);
+        assert_eq!(
+            start_state(&highlights, 2),
+            Some(HighlightState::BlockComment(1))
+        );
+        assert_eq!(
+            start_state(&highlights, 3),
+            Some(HighlightState::BlockComment(1))
+        );
+        assert_eq!(start_state(&highlights, 4), Some(HighlightState::Normal));
+    }
+
+    #[test]
+    fn closing_a_block_comment_rehighlights_the_lines_it_covered() {
+        let recorder = Recorder::default();
+        let mut lines = lines(&["/* a", "b", "c", "d"]);
+        let mut highlights = Highlights::new(lines.len());
+        highlights.update(&recorder, &lines, 3);
+        recorder.take();
+        assert_eq!(
+            start_state(&highlights, 3),
+            Some(HighlightState::BlockComment(1))
+        );
+
+        lines[0] = Line::from("/* a */");
+        highlights.invalidate(0, 1, 1);
+        highlights.update(&recorder, &lines, 1);
+        assert_eq!(recorder.take(), ["/* a */", "b"]);
+        highlights.update(&recorder, &lines, 3);
+        assert_eq!(recorder.take(), ["c", "d"]);
+        assert_eq!(start_state(&highlights, 3), Some(HighlightState::Normal));
+    }
+
+    #[test]
+    fn opening_a_raw_string_propagates_past_the_view() {
+        let recorder = Recorder::default();
+        let mut lines = lines(&["let s = 1;", "a", "b", "\"#;", "c"]);
+        let mut highlights = Highlights::new(lines.len());
+        highlights.update(&recorder, &lines, 4);
+        recorder.take();
+
+        lines[0] = Line::from("let s = r#\"");
+        highlights.invalidate(0, 1, 1);
+        highlights.update(&recorder, &lines, 0);
+        highlights.update(&recorder, &lines, 4);
+        // Before the edit, the quote on the fourth line opened a string that ran into the last one.
+        assert_eq!(recorder.take(), ["let s = r#\"", "a", "b", "\"#;", "c"]);
+        assert_eq!(
+            start_state(&highlights, 3),
+            Some(HighlightState::RawString(1))
+        );
+        assert_eq!(start_state(&highlights, 4), Some(HighlightState::Normal));
+    }
+
+    #[test]
+    fn inserted_and_removed_lines_shift_the_cache() {
+        let recorder = Recorder::default();
+        let mut lines = lines(TEXT);
+        let mut highlights = Highlights::new(lines.len());
+        highlights.update(&recorder, &lines, 4);
+        recorder.take();
+
+        lines.insert(2, Line::from("let x = 0;"));
+        highlights.invalidate(2, 0, 1);
+        highlights.update(&recorder, &lines, 5);
+        assert_eq!(recorder.take(), ["let x = 0;"]);
+
+        lines.remove(2);
+        highlights.invalidate(2, 1, 0);
+        highlights.update(&recorder, &lines, 4);
+        assert!(recorder.take().is_empty());
+    }
+}
@@ -0,0 +1,239 @@
+use super::{
+    annotate, end_of, is_digit, is_whitespace, starts_with, Annotation, AnnotationType,
+    HighlightState, SyntaxHighlighter,
+};
+
+const FENCES: [&str; 2] = ["```", "~~~"];
+
+/// Highlights headings, code fences, block quotes and list markers, as well as
+/// inline code, links and emphasis.
+pub struct Markdown;
+
+impl Markdown {
+    // Returns the end of a list marker (`- `, `* `, `+ `, `1. ` or `1) `) starting at `at`.
+    fn end_of_list_marker(graphemes: &[&str], at: usize) -> Option<usize> {
+        let &first = graphemes.get(at)?;
+        let marker_end = if matches!(first, "-" | "*" | "+") {
+            at.saturating_add(1)
+        } else if is_digit(first) {
+            let end = end_of(graphemes, at, is_digit);
+            if !matches!(graphemes.get(end), Some(&("." | ")"))) {
+                return None;
+            }
+            end.saturating_add(1)
+        } else {
+            return None;
+        };
+        graphemes
+            .get(marker_end)
+            .is_some_and(|grapheme| is_whitespace(grapheme))
+            .then_some(marker_end)
+    }
+
+    // Returns the end of a `[text](url)` link starting at `at`.
+    fn end_of_link(graphemes: &[&str], at: usize) -> Option<usize> {
+        let text_end = end_of(graphemes, at.saturating_add(1), |grapheme| grapheme != "]");
+        let url_start = text_end.saturating_add(1);
+        if graphemes.get(url_start) != Some(&"(") {
+            return None;
+        }
+        let url_end = end_of(graphemes, url_start, |grapheme| grapheme != ")");
+        (url_end < graphemes.len()).then_some(url_end.saturating_add(1))
+    }
+
+    // Returns the end of `*emphasis*` or `**strong emphasis**` (or the same with `_`) starting at `at`.
+    fn end_of_emphasis(graphemes: &[&str], at: usize) -> Option<usize> {
+        let &delimiter = graphemes.get(at)?;
+        let marker = if graphemes.get(at.saturating_add(1)) == Some(&delimiter) {
+            delimiter.repeat(2)
+        } else {
+            delimiter.to_string()
+        };
+        let content_start = at.saturating_add(marker.len());
+        if graphemes
+            .get(content_start)
+            .is_none_or(|grapheme| is_whitespace(grapheme))
+        {
+            return None;
+        }
+        (content_start..graphemes.len())
+            .find(|&index| starts_with(graphemes, index, &marker))
+            .map(|index| index.saturating_add(marker.len()))
+    }
+
+    fn highlight_inline(graphemes: &[&str], from: usize, annotations: &mut Vec<Annotation>) {
+        let mut index = from;
+        while let Some(&grapheme) = graphemes.get(index) {
+            let next = index.saturating_add(1);
+            let found = match grapheme {
+                "`" => {
+                    let closing = end_of(graphemes, next, |grapheme| grapheme != "`");
+                    Some((
+                        AnnotationType::String,
+                        closing.saturating_add(1).min(graphemes.len()),
+                    ))
+                }
+                "[" => Self::end_of_link(graphemes, index).map(|end| (AnnotationType::Type, end)),
+                "*" | "_" => {
+                    Self::end_of_emphasis(graphemes, index).map(|end| (AnnotationType::Type, end))
+                }
+                _ => None,
+            };
+            if let Some((annotation_type, end)) = found {
+                annotations.push(annotate(annotation_type, index..end));
+                index = end.max(next);
+            } else {
+                index = next;
+            }
+        }
+    }
+}
+
+impl SyntaxHighlighter for Markdown {
+    fn highlight(
+        &self,
+        graphemes: &[&str],
+        state: HighlightState,
+    ) -> (Vec<Annotation>, HighlightState) {
+        let mut annotations = Vec::new();
+        let start = end_of(graphemes, 0, is_whitespace);
+        // Inside a code block, everything up to the closing fence is code.
+        if let HighlightState::CodeBlock(fence) = state {
+            annotations.push(annotate(AnnotationType::String, 0..graphemes.len()));
+            let end_state = if starts_with(graphemes, start, fence) {
+                HighlightState::Normal
+            } else {
+                state
+            };
+            return (annotations, end_state);
+        }
+        let Some(&first) = graphemes.get(start) else {
+            return (annotations, state);
+        };
+        if let Some(&fence) = FENCES
+            .iter()
+            .find(|fence| starts_with(graphemes, start, fence))
+        {
+            annotations.push(annotate(AnnotationType::String, start..graphemes.len()));
+            return (annotations, HighlightState::CodeBlock(fence));
+        }
+        let whole_line = if first == "#" {
+            Some(AnnotationType::Keyword)
+        } else if first == ">" {
+            Some(AnnotationType::Comment)
+        } else {
+            None
+        };
+        if let Some(annotation_type) = whole_line {
+            annotations.push(annotate(annotation_type, start..graphemes.len()));
+            return (annotations, state);
+        }
+        let content_start = Self::end_of_list_marker(graphemes, start).map_or(start, |end| {
+            annotations.push(annotate(AnnotationType::Number, start..end));
+            end
+        });
+        Self::highlight_inline(graphemes, content_start, &mut annotations);
+        (annotations, state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use unicode_segmentation::UnicodeSegmentation;
+
+    use super::{AnnotationType, HighlightState, Markdown, SyntaxHighlighter};
+    use AnnotationType::{Comment, Keyword, Number, String as Str, Type};
+
+    const NORMAL: HighlightState = HighlightState::Normal;
+
+    // The expected annotations of a line, as pairs of type and annotated text.
+    type Parts<'a> = &'a [(AnnotationType, &'a str)];
+
+    fn check(cases: &[(HighlightState, &str, Parts, HighlightState)]) {
+        for &(state, line, expected, expected_state) in cases {
+            let graphemes: Vec<&str> = line.graphemes(true).collect();
+            let (annotations, end_state) = Markdown.highlight(&graphemes, state);
+            let parts: Vec<(AnnotationType, String)> = annotations
+                .into_iter()
+                .map(|annotation| {
+                    let text = graphemes.get(annotation.range).unwrap_or_default().concat();
+                    (annotation.annotation_type, text)
+                })
+                .collect();
+            let expected: Vec<(AnnotationType, String)> = expected
+                .iter()
+                .map(|&(annotation_type, text)| (annotation_type, text.to_string()))
+                .collect();
+            assert_eq!(parts, expected, "annotations of {line:?}");
+            assert_eq!(end_state, expected_state, "state after {line:?}");
+        }
+    }
+
+    #[test]
+    fn block_elements() {
+        check(&[
+            (
+                NORMAL,
+                "## Heading *x*",
+                &[(Keyword, "## Heading *x*")],
+                NORMAL,
+            ),
+            (NORMAL, "  > quoted", &[(Comment, "> quoted")], NORMAL),
+            (NORMAL, "- item", &[(Number, "-")], NORMAL),
+            (NORMAL, "12) item", &[(Number, "12)")], NORMAL),
+            (NORMAL, "-not a list", &[], NORMAL),
+            (NORMAL, "3.14 is a number", &[], NORMAL),
+            (NORMAL, "", &[], NORMAL),
+        ]);
+    }
+
+    #[test]
+    fn inline_elements() {
+        check(&[
+            (
+                NORMAL,
+                "* a `*code*` b",
+                &[(Number, "*"), (Str, "`*code*`")],
+                NORMAL,
+            ),
+            (
+                NORMAL,
+                "see [docs](http://x)",
+                &[(Type, "[docs](http://x)")],
+                NORMAL,
+            ),
+            (NORMAL, "[not a link] (x)", &[], NORMAL),
+            (
+                NORMAL,
+                "*em* and __strong__",
+                &[(Type, "*em*"), (Type, "__strong__")],
+                NORMAL,
+            ),
+            (NORMAL, "2 * 3 * 4", &[], NORMAL),
+            (NORMAL, "`unterminated", &[(Str, "`unterminated")], NORMAL),
+        ]);
+    }
+
+    #[test]
+    fn fenced_code_blocks() {
+        let backticks = HighlightState::CodeBlock("```");
+        check(&[
+            (NORMAL, "```rust", &[(Str, "```rust")], backticks),
+            (
+                backticks,
+                "# not a heading",
+                &[(Str, "# not a heading")],
+                backticks,
+            ),
+            // Only the fence that opened the block closes it.
+            (backticks, "~~~", &[(Str, "~~~")], backticks),
+            (backticks, "  ```", &[(Str, "  ```")], NORMAL),
+            (
+                NORMAL,
+                "~~~",
+                &[(Str, "~~~")],
+                HighlightState::CodeBlock("~~~"),
+            ),
+        ]);
+    }
+}
@@ -0,0 +1,587 @@
+use super::{
+    annotate, end_of, end_of_string, grapheme_count, is_digit, is_whitespace, is_word_part,
+    is_word_start, starts_with, Annotation, AnnotationType, HighlightState, SyntaxHighlighter,
+};
+
+/// A rule-based description of a language's syntax, covering keywords, types,
+/// strings, numbers and comments.
+// The flags are independent, language-specific rules rather than a hidden state machine.
+#[allow(clippy::struct_excessive_bools)]
+pub struct Syntax {
+    keywords: &'static [&'static str],
+    types: &'static [&'static str],
+    line_comment: Option<&'static str>,
+    // Opening and closing delimiter of block comments, which may be nested.
+    block_comment: Option<(&'static str, &'static str)>,
+    string_delimiters: &'static [&'static str],
+    // Delimiters of strings which may span multiple lines. These take precedence over `string_delimiters`.
+    multiline_string_delimiters: &'static [&'static str],
+    // `r"..."` and `r#"..."#` are raw strings (Rust).
+    raw_strings: bool,
+    // `'x'` is a character literal, but `'a` is a lifetime (Rust).
+    char_literals: bool,
+    // Identifiers starting with an uppercase letter are types (Rust).
+    capitalized_types: bool,
+    // Lines starting with `[` are table headers (TOML).
+    table_headers: bool,
+    // `$name`, `${name}` and `$1` are variables (Shell).
+    variables: bool,
+}
+
+pub static RUST: Syntax = Syntax {
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+        "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+        "true", "type", "unsafe", "use", "where", "while",
+    ],
+    types: &[
+        "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32",
+        "i64", "i128", "isize", "f32", "f64",
+    ],
+    line_comment: Some("//"),
+    block_comment: Some(("/*", "*/")),
+    string_delimiters: &[],
+    multiline_string_delimiters: &["\""],
+    raw_strings: true,
+    char_literals: true,
+    capitalized_types: true,
+    table_headers: false,
+    variables: false,
+};
+
+pub static TOML: Syntax = Syntax {
+    keywords: &["true", "false"],
+    types: &[],
+    line_comment: Some("#"),
+    block_comment: None,
+    string_delimiters: &["\"", "'"],
+    multiline_string_delimiters: &["\"\"\"", "'''"],
+    raw_strings: false,
+    char_literals: false,
+    capitalized_types: false,
+    table_headers: true,
+    variables: false,
+};
+
+pub static JSON: Syntax = Syntax {
+    keywords: &["true", "false", "null"],
+    types: &[],
+    line_comment: None,
+    block_comment: None,
+    string_delimiters: &["\""],
+    multiline_string_delimiters: &[],
+    raw_strings: false,
+    char_literals: false,
+    capitalized_types: false,
+    table_headers: false,
+    variables: false,
+};
+
+pub static SHELL: Syntax = Syntax {
+    keywords: &[
+        "if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done", "case", "esac",
+        "in", "function", "return", "local", "export", "readonly", "declare", "unset", "shift",
+        "exit", "break", "continue", "source",
+    ],
+    types: &[],
+    line_comment: Some("#"),
+    block_comment: None,
+    string_delimiters: &["`"],
+    multiline_string_delimiters: &["\"", "'"],
+    raw_strings: false,
+    char_literals: false,
+    capitalized_types: false,
+    table_headers: false,
+    variables: true,
+};
+
+impl Syntax {
+    // Returns the end of a character literal starting at `at`, or `None` if it's a lifetime.
+    fn end_of_char_literal(graphemes: &[&str], at: usize) -> Option<usize> {
+        let next = at.saturating_add(1);
+        if graphemes.get(next) == Some(&"\\") {
+            return Some(end_of_string(graphemes, next, "'"));
+        }
+        let closing = at.saturating_add(2);
+        (graphemes.get(closing) == Some(&"'")).then_some(closing.saturating_add(1))
+    }
+
+    // Numbers may contain letters and underscores (`0xFF`, `1_000u32`) and a decimal point
+    // if a digit follows it, so that ranges like `0..10` aren't swallowed.
+    fn end_of_number(graphemes: &[&str], at: usize) -> usize {
+        let mut index = at;
+        while let Some(&grapheme) = graphemes.get(index) {
+            let next = index.saturating_add(1);
+            let is_decimal_point =
+                grapheme == "." && graphemes.get(next).is_some_and(|next| is_digit(next));
+            if !is_word_part(grapheme) && !is_decimal_point {
+                break;
+            }
+            index = next;
+        }
+        index
+    }
+
+    // Returns the end of a variable reference starting at `$`, or `None` if the `$` stands alone.
+    fn end_of_variable(graphemes: &[&str], at: usize) -> Option<usize> {
+        let next = at.saturating_add(1);
+        let &grapheme = graphemes.get(next)?;
+        if grapheme == "{" {
+            let closing = end_of(graphemes, next, |grapheme| grapheme != "}");
+            Some(closing.saturating_add(1).min(graphemes.len()))
+        } else if is_word_start(grapheme) {
+            Some(end_of(graphemes, next, is_word_part))
+        } else if is_digit(grapheme) || matches!(grapheme, "#" | "?" | "@" | "*" | "$" | "!") {
+            Some(next.saturating_add(1))
+        } else {
+            None
+        }
+    }
+
+    // Returns the state opened by a block comment, multi-line string or raw string starting at `at`,
+    // together with the index its content starts at.
+    fn start_of_construct(&self, graphemes: &[&str], at: usize) -> Option<(HighlightState, usize)> {
+        if let Some((open, _)) = self
+            .block_comment
+            .filter(|(open, _)| starts_with(graphemes, at, open))
+        {
+            return Some((
+                HighlightState::BlockComment(1),
+                at.saturating_add(grapheme_count(open)),
+            ));
+        }
+        if let Some(&delimiter) = self
+            .multiline_string_delimiters
+            .iter()
+            .find(|delimiter| starts_with(graphemes, at, delimiter))
+        {
+            return Some((
+                HighlightState::String(delimiter),
+                at.saturating_add(grapheme_count(delimiter)),
+            ));
+        }
+        if !self.raw_strings {
+            return None;
+        }
+        // Raw strings look like `r"..."`, `r#"..."#` or `br##"..."##`.
+        let mut index = at;
+        if graphemes.get(index) == Some(&"b") {
+            index = index.saturating_add(1);
+        }
+        if graphemes.get(index) != Some(&"r") {
+            return None;
+        }
+        let hashes_start = index.saturating_add(1);
+        let quote = end_of(graphemes, hashes_start, |grapheme| grapheme == "#");
+        (graphemes.get(quote) == Some(&"\"")).then_some((
+            HighlightState::RawString(quote.saturating_sub(hashes_start)),
+            quote.saturating_add(1),
+        ))
+    }
+
+    // Scans the content of the construct described by `state` from `from` and returns the index
+    // after its end together with `HighlightState::Normal`, or the end of the line together with
+    // the state the construct is still in if it doesn't end on this line.
+    fn end_of_construct(
+        &self,
+        graphemes: &[&str],
+        from: usize,
+        state: HighlightState,
+    ) -> (usize, HighlightState) {
+        let mut index = from;
+        let mut state = state;
+        while let Some(&grapheme) = graphemes.get(index) {
+            let next = index.saturating_add(1);
+            match state {
+                HighlightState::BlockComment(depth) => {
+                    let Some((open, close)) = self.block_comment else {
+                        break;
+                    };
+                    if starts_with(graphemes, index, close) {
+                        index = index.saturating_add(grapheme_count(close));
+                        if depth <= 1 {
+                            return (index, HighlightState::Normal);
+                        }
+                        state = HighlightState::BlockComment(depth.saturating_sub(1));
+                        continue;
+                    }
+                    if starts_with(graphemes, index, open) {
+                        index = index.saturating_add(grapheme_count(open));
+                        state = HighlightState::BlockComment(depth.saturating_add(1));
+                        continue;
+                    }
+                }
+                HighlightState::String(delimiter) => {
+                    if grapheme == "\\" {
+                        index = index.saturating_add(2);
+                        continue;
+                    }
+                    if starts_with(graphemes, index, delimiter) {
+                        return (
+                            index.saturating_add(grapheme_count(delimiter)),
+                            HighlightState::Normal,
+                        );
+                    }
+                }
+                HighlightState::RawString(hashes) => {
+                    let hashes_end = next.saturating_add(hashes);
+                    if grapheme == "\""
+                        && graphemes
+                            .get(next..hashes_end)
+                            .is_some_and(|closing| closing.iter().all(|&hash| hash == "#"))
+                    {
+                        return (hashes_end, HighlightState::Normal);
+                    }
+                }
+                HighlightState::Normal | HighlightState::CodeBlock(_) => return (index, state),
+            }
+            index = next;
+        }
+        (graphemes.len(), state)
+    }
+
+    fn word_type(&self, word: &str) -> Option<AnnotationType> {
+        if self.keywords.contains(&word) {
+            Some(AnnotationType::Keyword)
+        } else if self.types.contains(&word)
+            || (self.capitalized_types && word.chars().next().is_some_and(char::is_uppercase))
+        {
+            Some(AnnotationType::Type)
+        } else {
+            None
+        }
+    }
+}
+
+impl SyntaxHighlighter for Syntax {
+    fn highlight(
+        &self,
+        graphemes: &[&str],
+        state: HighlightState,
+    ) -> (Vec<Annotation>, HighlightState) {
+        let mut annotations = Vec::new();
+        // Finish whatever the previous line left open first.
+        let (mut index, state) = if let Some(annotation_type) = state.annotation_type() {
+            let (end, end_state) = self.end_of_construct(graphemes, 0, state);
+            annotations.push(annotate(annotation_type, 0..end));
+            (end, end_state)
+        } else {
+            if self.table_headers
+                && graphemes.iter().find(|grapheme| !is_whitespace(grapheme)) == Some(&"[")
+            {
+                annotations.push(annotate(AnnotationType::Type, 0..graphemes.len()));
+                return (annotations, state);
+            }
+            (0, state)
+        };
+        if state != HighlightState::Normal {
+            return (annotations, state);
+        }
+        while let Some(&grapheme) = graphemes.get(index) {
+            let next = index.saturating_add(1);
+            if self
+                .line_comment
+                .is_some_and(|comment| starts_with(graphemes, index, comment))
+            {
+                annotations.push(annotate(AnnotationType::Comment, index..graphemes.len()));
+                break;
+            }
+            if let Some((opened, content_start)) = self.start_of_construct(graphemes, index) {
+                let (end, end_state) = self.end_of_construct(graphemes, content_start, opened);
+                if let Some(annotation_type) = opened.annotation_type() {
+                    annotations.push(annotate(annotation_type, index..end));
+                }
+                if end_state != HighlightState::Normal {
+                    return (annotations, end_state);
+                }
+                index = end.max(next);
+                continue;
+            }
+            let (annotation_type, end) = if self.string_delimiters.contains(&grapheme) {
+                let end = end_of_string(graphemes, next, grapheme);
+                (Some(AnnotationType::String), end)
+            } else if self.char_literals && grapheme == "'" {
+                Self::end_of_char_literal(graphemes, index).map_or_else(
+                    || {
+                        (
+                            Some(AnnotationType::Type),
+                            end_of(graphemes, next, is_word_part),
+                        )
+                    },
+                    |end| (Some(AnnotationType::String), end),
+                )
+            } else if self.variables && grapheme == "$" {
+                Self::end_of_variable(graphemes, index)
+                    .map_or((None, next), |end| (Some(AnnotationType::Type), end))
+            } else if is_digit(grapheme) {
+                (
+                    Some(AnnotationType::Number),
+                    Self::end_of_number(graphemes, index),
+                )
+            } else if is_word_start(grapheme) {
+                let end = end_of(graphemes, index, is_word_part);
+                let word: String = graphemes.get(index..end).unwrap_or_default().concat();
+                (self.word_type(&word), end)
+            } else {
+                (None, next)
+            };
+            if let Some(annotation_type) = annotation_type {
+                annotations.push(annotate(annotation_type, index..end));
+            }
+            index = end.max(next);
+        }
+        (annotations, HighlightState::Normal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use unicode_segmentation::UnicodeSegmentation;
+
+    use super::{
+        AnnotationType, HighlightState, Syntax, SyntaxHighlighter, JSON, RUST, SHELL, TOML,
+    };
+    use AnnotationType::{Comment, Keyword, Number, String as Str, Type};
+
+    // Highlights `line` starting in `state` and returns the annotated parts of the line.
+    fn highlight(
+        syntax: &Syntax,
+        line: &str,
+        state: HighlightState,
+    ) -> (Vec<(AnnotationType, String)>, HighlightState) {
+        let graphemes: Vec<&str> = line.graphemes(true).collect();
+        let (annotations, end_state) = syntax.highlight(&graphemes, state);
+        let parts = annotations
+            .into_iter()
+            .map(|annotation| {
+                let text = graphemes.get(annotation.range).unwrap_or_default().concat();
+                (annotation.annotation_type, text)
+            })
+            .collect();
+        (parts, end_state)
+    }
+
+    // The expected annotations of a line, as pairs of type and annotated text.
+    type Parts<'a> = &'a [(AnnotationType, &'a str)];
+
+    fn assert_highlights(
+        syntax: &Syntax,
+        state: HighlightState,
+        line: &str,
+        expected: Parts,
+        expected_state: HighlightState,
+    ) {
+        let (parts, end_state) = highlight(syntax, line, state);
+        let expected: Vec<(AnnotationType, String)> = expected
+            .iter()
+            .map(|&(annotation_type, text)| (annotation_type, text.to_string()))
+            .collect();
+        assert_eq!(parts, expected, "annotations of {line:?}");
+        assert_eq!(end_state, expected_state, "state after {line:?}");
+    }
+
+    fn check(syntax: &Syntax, cases: &[(&str, Parts, HighlightState)]) {
+        for &(line, expected, expected_state) in cases {
+            assert_highlights(
+                syntax,
+                HighlightState::Normal,
+                line,
+                expected,
+                expected_state,
+            );
+        }
+    }
+
+    const NORMAL: HighlightState = HighlightState::Normal;
+
+    #[test]
+    fn rust_tokens() {
+        check(
+            &RUST,
+            &[
+                ("let x = 42;", &[(Keyword, "let"), (Number, "42")], NORMAL),
+                (
+                    "fn f() -> Vec<u8>",
+                    &[(Keyword, "fn"), (Type, "Vec"), (Type, "u8")],
+                    NORMAL,
+                ),
+                ("letter", &[], NORMAL),
+                (
+                    "0..10 1.5 0xFF 1_000u32",
+                    &[
+                        (Number, "0"),
+                        (Number, "10"),
+                        (Number, "1.5"),
+                        (Number, "0xFF"),
+                        (Number, "1_000u32"),
+                    ],
+                    NORMAL,
+                ),
+                ("x // let y", &[(Comment, "// let y")], NORMAL),
+            ],
+        );
+    }
+
+    #[test]
+    fn rust_strings_and_chars() {
+        check(
+            &RUST,
+            &[
+                (
+                    r#"s = "a \"quoted\" b"; x"#,
+                    &[(Str, r#""a \"quoted\" b""#)],
+                    NORMAL,
+                ),
+                (r#""back\\" x"#, &[(Str, r#""back\\""#)], NORMAL),
+                (
+                    r"'a' '\'' &'static",
+                    &[(Str, "'a'"), (Str, r"'\''"), (Type, "'static")],
+                    NORMAL,
+                ),
+                (
+                    "r#\"raw \"quote\"\"# x",
+                    &[(Str, "r#\"raw \"quote\"\"#")],
+                    NORMAL,
+                ),
+                (
+                    "\"unterminated",
+                    &[(Str, "\"unterminated")],
+                    HighlightState::String("\""),
+                ),
+                (
+                    r#"br##"open"#,
+                    &[(Str, r#"br##"open"#)],
+                    HighlightState::RawString(2),
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn rust_block_comments_nest() {
+        check(
+            &RUST,
+            &[
+                (
+                    "a /* b /* c */ d */ let",
+                    &[(Comment, "/* b /* c */ d */"), (Keyword, "let")],
+                    NORMAL,
+                ),
+                (
+                    "/* open",
+                    &[(Comment, "/* open")],
+                    HighlightState::BlockComment(1),
+                ),
+                (
+                    "/* a /* b */",
+                    &[(Comment, "/* a /* b */")],
+                    HighlightState::BlockComment(1),
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn continues_constructs_from_the_previous_line() {
+        let cases: &[(HighlightState, &str, Parts, HighlightState)] = &[
+            (
+                HighlightState::BlockComment(2),
+                "x */ y */ let",
+                &[(Comment, "x */ y */"), (Keyword, "let")],
+                NORMAL,
+            ),
+            (
+                HighlightState::BlockComment(1),
+                "/* deeper",
+                &[(Comment, "/* deeper")],
+                HighlightState::BlockComment(2),
+            ),
+            (
+                HighlightState::String("\""),
+                r#"a \" b" fn"#,
+                &[(Str, r#"a \" b""#), (Keyword, "fn")],
+                NORMAL,
+            ),
+            (
+                HighlightState::RawString(1),
+                r##"" not the end "# 1"##,
+                &[(Str, r##"" not the end "#"##), (Number, "1")],
+                NORMAL,
+            ),
+        ];
+        for &(state, line, expected, expected_state) in cases {
+            assert_highlights(&RUST, state, line, expected, expected_state);
+        }
+    }
+
+    #[test]
+    fn json_tokens() {
+        check(
+            &JSON,
+            &[
+                (
+                    r#"{"k": "va\"l", "n": -1.5e3, "t": true, "z": null}"#,
+                    &[
+                        (Str, r#""k""#),
+                        (Str, r#""va\"l""#),
+                        (Str, r#""n""#),
+                        (Number, "1.5e3"),
+                        (Str, r#""t""#),
+                        (Keyword, "true"),
+                        (Str, r#""z""#),
+                        (Keyword, "null"),
+                    ],
+                    NORMAL,
+                ),
+                // Strings can't span lines in JSON, so an unterminated one ends with the line.
+                ("\"open", &[(Str, "\"open")], NORMAL),
+            ],
+        );
+    }
+
+    #[test]
+    fn toml_tokens() {
+        check(
+            &TOML,
+            &[
+                ("  [table.sub]", &[(Type, "  [table.sub]")], NORMAL),
+                (
+                    "key = 'lit' # note",
+                    &[(Str, "'lit'"), (Comment, "# note")],
+                    NORMAL,
+                ),
+                ("on = true", &[(Keyword, "true")], NORMAL),
+                (
+                    "text = \"\"\"",
+                    &[(Str, "\"\"\"")],
+                    HighlightState::String("\"\"\""),
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn shell_tokens() {
+        check(
+            &SHELL,
+            &[
+                (
+                    "if [ $1 ]; then echo ${x} $HOME; fi # done",
+                    &[
+                        (Keyword, "if"),
+                        (Type, "$1"),
+                        (Keyword, "then"),
+                        (Type, "${x}"),
+                        (Type, "$HOME"),
+                        (Keyword, "fi"),
+                        (Comment, "# done"),
+                    ],
+                    NORMAL,
+                ),
+                ("echo \"a $b\" $", &[(Str, "\"a $b\"")], NORMAL),
+                ("echo 'open", &[(Str, "'open")], HighlightState::String("'")),
+            ],
+        );
+    }
+}
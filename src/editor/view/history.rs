@@ -0,0 +1,267 @@
+use std::mem;
+
+use super::Location;
+
+// How many undo steps are kept. The oldest ones are dropped beyond that.
+const MAX_STEPS: usize = 1000;
+
+/// A single modification of the buffer: starting at `line_index`, the lines in `removed`
+/// were replaced by the lines in `inserted`.
+#[derive(Clone)]
+pub struct Change {
+    pub line_index: usize,
+    pub removed: Vec<String>,
+    pub inserted: Vec<String>,
+}
+
+impl Change {
+    // Folds `next` into this change if it only rewrites the single line this change produced,
+    // which is what happens while typing on the same line.
+    fn try_merge(&mut self, next: &Self) -> bool {
+        if self.line_index == next.line_index
+            && self.inserted.len() == 1
+            && next.removed == self.inserted
+        {
+            self.inserted.clone_from(&next.inserted);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// One undoable step, made up of all changes caused by one or more grouped edit commands.
+pub struct Step {
+    id: usize,
+    pub changes: Vec<Change>,
+    pub caret_before: Location,
+    pub caret_after: Location,
+}
+
+impl Step {
+    fn push(&mut self, change: Change) {
+        let merged = self
+            .changes
+            .last_mut()
+            .is_some_and(|last| last.try_merge(&change));
+        if !merged {
+            self.changes.push(change);
+        }
+    }
+}
+
+/// The undo and redo stacks of a buffer. At most `MAX_STEPS` steps can be undone.
+#[derive(Default)]
+pub struct History {
+    undo_stack: Vec<Step>,
+    redo_stack: Vec<Step>,
+    pending: Vec<Change>,
+    next_id: usize,
+    saved_id: usize,
+    // The id of the newest step dropped off the bottom of the undo stack, which is the state
+    // the text is in once every remaining step has been undone.
+    base_id: usize,
+    // Whether the topmost undo step may still absorb the next edit.
+    open: bool,
+}
+
+impl History {
+    /// Records a change that has already been applied to the buffer.
+    /// It becomes undoable once `commit` is called.
+    pub fn record(&mut self, change: Change) {
+        self.pending.push(change);
+        self.redo_stack.clear();
+    }
+
+    /// Turns all changes recorded since the last commit into an undo step.
+    /// If `coalesce` is set and the previous step was coalescable as well, the changes are
+    /// appended to the previous step instead, so that consecutive typing is undone at once.
+    pub fn commit(&mut self, caret_before: Location, caret_after: Location, coalesce: bool) {
+        let changes = mem::take(&mut self.pending);
+        if changes.is_empty() {
+            self.open = self.open && coalesce;
+            return;
+        }
+        let saved_id = self.saved_id;
+        let open = self.open;
+        let target = self.undo_stack.last_mut().filter(|step| {
+            coalesce && open && step.id != saved_id && step.caret_after == caret_before
+        });
+        if let Some(step) = target {
+            for change in changes {
+                step.push(change);
+            }
+            step.caret_after = caret_after;
+        } else {
+            self.next_id = self.next_id.saturating_add(1);
+            let mut step = Step {
+                id: self.next_id,
+                changes: Vec::new(),
+                caret_before,
+                caret_after,
+            };
+            for change in changes {
+                step.push(change);
+            }
+            self.undo_stack.push(step);
+            if self.undo_stack.len() > MAX_STEPS {
+                let dropped = self.undo_stack.remove(0);
+                self.base_id = dropped.id;
+            }
+        }
+        self.open = coalesce;
+    }
+
+    /// Prevents the next edit from being grouped with the previous one.
+    pub fn seal(&mut self) {
+        self.open = false;
+    }
+
+    /// Pops the most recent step, moves it to the redo stack and returns it so its changes
+    /// can be reverted.
+    pub fn undo(&mut self) -> Option<&Step> {
+        let step = self.undo_stack.pop()?;
+        self.open = false;
+        self.redo_stack.push(step);
+        self.redo_stack.last()
+    }
+
+    /// Pops the most recently undone step, moves it back to the undo stack and returns it so
+    /// its changes can be re-applied.
+    pub fn redo(&mut self) -> Option<&Step> {
+        let step = self.redo_stack.pop()?;
+        self.open = false;
+        self.undo_stack.push(step);
+        self.undo_stack.last()
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.saved_id = self.current_id();
+        self.open = false;
+    }
+
+    pub fn is_at_saved_state(&self) -> bool {
+        self.current_id() == self.saved_id
+    }
+
+    fn current_id(&self) -> usize {
+        self.undo_stack.last().map_or(self.base_id, |step| step.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Change, History, Location, MAX_STEPS};
+
+    fn at(grapheme_index: usize) -> Location {
+        Location {
+            grapheme_index,
+            line_index: 0,
+        }
+    }
+
+    // Records typing one character at `column` of a line that read `before`.
+    fn type_char(history: &mut History, before: &str, column: usize, coalesce: bool) -> String {
+        let after = format!("{before}x");
+        history.record(Change {
+            line_index: 0,
+            removed: vec![before.to_string()],
+            inserted: vec![after.clone()],
+        });
+        history.commit(at(column), at(column.saturating_add(1)), coalesce);
+        after
+    }
+
+    fn undo_count(history: &mut History) -> usize {
+        let mut count: usize = 0;
+        while history.undo().is_some() {
+            count = count.saturating_add(1);
+        }
+        count
+    }
+
+    #[test]
+    fn consecutive_typing_is_one_step() {
+        let mut history = History::default();
+        let line = type_char(&mut history, "", 0, true);
+        let line = type_char(&mut history, &line, 1, true);
+        type_char(&mut history, &line, 2, true);
+        let step = history.undo().unwrap();
+        assert_eq!(step.changes.len(), 1);
+        assert_eq!(step.changes[0].removed, vec![String::new()]);
+        assert_eq!(step.changes[0].inserted, vec![String::from("xxx")]);
+        assert!(step.caret_before == at(0) && step.caret_after == at(3));
+        assert!(history.undo().is_none());
+    }
+
+    #[test]
+    fn moving_the_caret_starts_a_new_step() {
+        let mut history = History::default();
+        let line = type_char(&mut history, "", 0, true);
+        // The caret moved back to the start before typing again.
+        type_char(&mut history, &line, 0, true);
+        assert_eq!(undo_count(&mut history), 2);
+    }
+
+    #[test]
+    fn sealing_or_not_coalescing_starts_a_new_step() {
+        let mut history = History::default();
+        let line = type_char(&mut history, "", 0, true);
+        history.seal();
+        let line = type_char(&mut history, &line, 1, true);
+        type_char(&mut history, &line, 2, false);
+        assert_eq!(undo_count(&mut history), 3);
+    }
+
+    #[test]
+    fn a_new_edit_clears_redo() {
+        let mut history = History::default();
+        type_char(&mut history, "", 0, false);
+        history.undo();
+        type_char(&mut history, "", 0, false);
+        assert!(history.redo().is_none());
+    }
+
+    #[test]
+    fn undo_then_redo_returns_to_the_saved_state() {
+        let mut history = History::default();
+        assert!(history.is_at_saved_state());
+        let line = type_char(&mut history, "", 0, false);
+        history.mark_saved();
+        type_char(&mut history, &line, 1, true);
+        assert!(!history.is_at_saved_state());
+        history.undo();
+        assert!(history.is_at_saved_state());
+        history.undo();
+        assert!(!history.is_at_saved_state());
+        history.redo();
+        assert!(history.is_at_saved_state());
+        history.redo();
+        assert!(!history.is_at_saved_state());
+    }
+
+    #[test]
+    fn typing_right_after_saving_starts_a_new_step() {
+        let mut history = History::default();
+        let line = type_char(&mut history, "", 0, true);
+        history.mark_saved();
+        type_char(&mut history, &line, 1, true);
+        history.undo();
+        assert!(history.is_at_saved_state());
+    }
+
+    #[test]
+    fn keeps_at_most_max_steps() {
+        let mut history = History::default();
+        let mut line = String::new();
+        for column in 0..MAX_STEPS + 10 {
+            line = type_char(&mut history, &line, column, false);
+            if column == 5 {
+                history.mark_saved();
+            }
+        }
+        assert_eq!(undo_count(&mut history), MAX_STEPS);
+        // The saved state was dropped, so undoing everything can't get back to it.
+        assert!(!history.is_at_saved_state());
+    }
+}
@@ -0,0 +1,13 @@
+use super::{Location, SearchQuery};
+
+// Keeps track of an ongoing search and replace session.
+pub struct ReplaceInfo {
+    pub query: SearchQuery,
+    pub replacement: String,
+    pub prev_location: Location,
+    pub replaced: usize,
+    // Where the walk through the matches started. It wraps around at the end of the buffer
+    // and ends once it gets back there.
+    pub start: Location,
+    pub wrapped: bool,
+}
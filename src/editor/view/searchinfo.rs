@@ -0,0 +1,9 @@
+use super::{Location, Position, SearchQuery};
+
+// Keeps track of an ongoing search, so the caret can be restored when the search is dismissed.
+pub struct SearchInfo {
+    pub prev_location: Location,
+    pub prev_scroll_offset: Position,
+    pub prev_top_wrapped_row: usize,
+    pub query: Option<SearchQuery>,
+}
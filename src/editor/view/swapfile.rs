@@ -0,0 +1,122 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{Error, ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+// How often unsaved changes are written to the swap file at most.
+const WRITE_INTERVAL: Duration = Duration::from_secs(2);
+// Named after this editor rather than Vim's `.swp`, so that we never mistake the swap file of a
+// running Vim for ours.
+const EXTENSION: &str = "j1ee-swp";
+
+/// A copy of the unsaved text of a buffer, kept next to its file as `.name.j1ee-swp`
+/// so that the changes can be recovered if the editor dies before saving.
+/// Buffers without a file have nowhere to keep it, so their changes can't be recovered.
+#[derive(Default)]
+pub struct SwapFile {
+    path: Option<PathBuf>,
+    // Whether the buffer changed since the swap file was last written.
+    is_stale: bool,
+    // Whether the swap file on disk was written by us, and thus ours to clean up.
+    is_written: bool,
+    last_written: Option<Instant>,
+}
+
+impl SwapFile {
+    pub fn for_file(file_path: Option<&Path>) -> Self {
+        let path = file_path.and_then(|file_path| {
+            let file_name = file_path.file_name()?.to_str()?;
+            Some(file_path.with_file_name(format!(".{file_name}.{EXTENSION}")))
+        });
+        Self {
+            path,
+            ..Self::default()
+        }
+    }
+    pub fn mark_stale(&mut self) {
+        self.is_stale = true;
+    }
+    pub fn is_due(&self) -> bool {
+        self.is_stale
+            && self
+                .last_written
+                .is_none_or(|last_written| last_written.elapsed() >= WRITE_INTERVAL)
+    }
+    pub fn write(&mut self, text: &str) -> Result<(), Error> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        // Write to a temporary file first, so that a crash mid-write can't destroy the last good copy.
+        let mut temp_path = path.clone().into_os_string();
+        temp_path.push(".tmp");
+        // A leftover from a crash may have been created with other permissions.
+        let _ = fs::remove_file(&temp_path);
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        restrict_to_owner(&mut options);
+        let result = options
+            .open(&temp_path)
+            .and_then(|mut file| file.write_all(text.as_bytes()))
+            .and_then(|()| fs::rename(&temp_path, path));
+        if let Err(err) = result {
+            let _ = fs::remove_file(&temp_path);
+            return Err(err);
+        }
+        self.is_stale = false;
+        self.is_written = true;
+        self.last_written = Some(Instant::now());
+        Ok(())
+    }
+    /// Removes the swap file if it was written by us.
+    pub fn remove(&mut self) {
+        if self.is_written {
+            self.discard();
+        }
+    }
+    /// Removes the swap file, even if it was left behind by an earlier session.
+    pub fn discard(&mut self) {
+        if let Some(path) = &self.path {
+            let _ = fs::remove_file(path);
+        }
+        self.is_stale = false;
+        self.is_written = false;
+    }
+    /// Checks whether a swap file left behind by an earlier session exists that is newer than
+    /// the file it belongs to, which means it holds changes that were never saved.
+    pub fn is_recoverable(&self, file_path: &Path) -> bool {
+        if self.is_written {
+            return false;
+        }
+        let Some(swap_modified) = self.path.as_ref().and_then(|path| {
+            fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        }) else {
+            return false;
+        };
+        fs::metadata(file_path)
+            .and_then(|metadata| metadata.modified())
+            .map_or(true, |file_modified| swap_modified > file_modified)
+    }
+    /// Reads the text kept in the swap file, which from now on counts as ours.
+    pub fn recover(&mut self) -> Result<String, Error> {
+        let Some(path) = &self.path else {
+            return Err(Error::new(ErrorKind::NotFound, "there is no swap file"));
+        };
+        let text = fs::read_to_string(path)?;
+        self.is_written = true;
+        Ok(text)
+    }
+}
+
+// The swap file holds the text of the file, which may be private, so only the owner may read it.
+#[cfg(unix)]
+fn restrict_to_owner(options: &mut OpenOptions) {
+    use std::os::unix::fs::OpenOptionsExt;
+    options.mode(0o600);
+}
+
+#[cfg(not(unix))]
+fn restrict_to_owner(_options: &mut OpenOptions) {}
@@ -0,0 +1,67 @@
+use super::{Position, Size, StatusBar, UIComponent, View};
+
+/// A view together with the status line below it, placed somewhere on the screen.
+#[derive(Default)]
+pub struct Window {
+    pub view: View,
+    status_bar: StatusBar,
+    // Relative to the origin of the layout.
+    origin: Position,
+    view_height: usize,
+}
+
+impl Window {
+    pub fn new(view: View) -> Self {
+        Self {
+            view,
+            ..Self::default()
+        }
+    }
+    /// Moves the window to `origin` and resizes it, giving its last row to the status line.
+    pub fn place(&mut self, origin: Position, size: Size) {
+        self.origin = origin;
+        self.view_height = size.height.saturating_sub(1);
+        self.view.resize(Size {
+            height: self.view_height,
+            width: size.width,
+        });
+        self.status_bar.resize(Size {
+            height: 1,
+            width: size.width,
+        });
+    }
+    pub fn refresh_status(&mut self) {
+        self.status_bar.update_status(self.view.get_status());
+    }
+    pub fn needs_redraw(&self) -> bool {
+        self.view.needs_redraw() || self.status_bar.needs_redraw()
+    }
+    pub fn set_needs_redraw(&mut self, value: bool) {
+        self.view.set_needs_redraw(value);
+        self.status_bar.set_needs_redraw(value);
+    }
+    pub fn render(&mut self, layout_origin: Position) {
+        let origin = self.absolute(Position::default(), layout_origin);
+        self.view.render(origin);
+        self.status_bar.render(Position {
+            row: origin.row.saturating_add(self.view_height),
+            col: origin.col,
+        });
+    }
+    pub fn caret_position(&self, layout_origin: Position) -> Position {
+        self.absolute(self.view.caret_position(), layout_origin)
+    }
+    // Turns a position within the window into one on the screen.
+    const fn absolute(&self, position: Position, layout_origin: Position) -> Position {
+        Position {
+            row: layout_origin
+                .row
+                .saturating_add(self.origin.row)
+                .saturating_add(position.row),
+            col: layout_origin
+                .col
+                .saturating_add(self.origin.col)
+                .saturating_add(position.col),
+        }
+    }
+}